@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serverstat::qtv::QtvStream;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = QtvStream::try_from(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serverstat::svc_qtvusers::QtvusersResponse;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = QtvusersResponse::try_from(data);
+});
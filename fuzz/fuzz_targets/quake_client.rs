@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serverstat::client::QuakeClient;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = QuakeClient::try_from(data);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serverstat::status_flags::StatusFlags;
+use serverstat::svc_status::StatusResponse;
+
+// first byte is used as status flags
+fuzz_target!(|data: &[u8]| {
+    if let Some((flags, bytes)) = data.split_first() {
+        let _ = StatusResponse::parse(bytes, StatusFlags::from_bits(*flags));
+    }
+});
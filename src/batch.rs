@@ -0,0 +1,124 @@
+use crate::engine::QueryEngine;
+use crate::error::{Error, Result};
+use crate::rt;
+use crate::server::QuakeServer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryManyOptions {
+    /// Max number of queries in flight at the same time.
+    pub concurrency: usize,
+    /// Timeout for each query.
+    pub timeout: Duration,
+    /// Max duration of the whole batch, queries not completed by then fail
+    /// with [`Error::Timeout`].
+    pub deadline: Option<Duration>,
+}
+
+impl Default for QueryManyOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 64,
+            timeout: Duration::from_secs(1),
+            deadline: None,
+        }
+    }
+}
+
+/// Query many servers concurrently.
+///
+/// All queries are sent from one shared socket per address family, see
+/// [`QueryEngine`].
+/// Returns one result per address, in the same order as given, or an error
+/// if the socket could not be bound.
+pub async fn query_many<S: AsRef<str>>(
+    addresses: &[S],
+    options: QueryManyOptions,
+) -> Result<Vec<(String, Result<QuakeServer>)>> {
+    let engine = QueryEngine::bind().await?;
+    let deadline = options.deadline.map(|d| Instant::now() + d);
+    let next_index = AtomicUsize::new(0);
+
+    // each worker queries the next address until all are done
+    let worker = || async {
+        let mut results = vec![];
+
+        loop {
+            let index = next_index.fetch_add(1, Ordering::Relaxed);
+            let Some(address) = addresses.get(index) else {
+                return results;
+            };
+            let address = address.as_ref().to_string();
+            let query = engine.query(&address, options.timeout);
+            let result = match deadline {
+                Some(deadline) => {
+                    rt::timeout(deadline.saturating_duration_since(Instant::now()), query)
+                        .await
+                        .unwrap_or(Err(Error::Timeout))
+                }
+                None => query.await,
+            };
+            results.push((index, address, result));
+        }
+    };
+    let workers = options.concurrency.clamp(1, addresses.len().max(1));
+
+    let mut results: Vec<(usize, String, Result<QuakeServer>)> =
+        rt::join_all((0..workers).map(|_| worker()))
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+    results.sort_by_key(|(index, _, _)| *index);
+    Ok(results
+        .into_iter()
+        .map(|(_, address, result)| (address, result))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_query_many() -> anyhow::Result<()> {
+        let options = QueryManyOptions {
+            concurrency: 2,
+            timeout: Duration::from_millis(50),
+            deadline: None,
+        };
+        let results = query_many(&["foo.bar:666", "127.0.0.1:1", "bar.foo:666"], options).await?;
+        let addresses: Vec<&str> = results.iter().map(|(a, _)| a.as_str()).collect();
+
+        assert_eq!(addresses, vec!["foo.bar:666", "127.0.0.1:1", "bar.foo:666"]);
+        assert!(results.iter().all(|(_, res)| res.is_err()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_many_deadline() -> anyhow::Result<()> {
+        let options = QueryManyOptions {
+            concurrency: 1,
+            timeout: Duration::from_secs(5),
+            deadline: Some(Duration::from_millis(50)),
+        };
+        let results = query_many(&["127.0.0.1:1", "127.0.0.1:2"], options).await?;
+
+        assert_eq!(results.len(), 2);
+        for (_, res) in results {
+            assert!(matches!(res, Err(Error::Timeout)));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_many_mock() -> anyhow::Result<()> {
+        let mock = MockServer::mvdsv("QUAKE.SE KTX:28501").spawn()?;
+        let results = query_many(&[mock.address()], QueryManyOptions::default()).await?;
+        assert!(results[0].1.is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Blocking queries using [`std::net::UdpSocket`], no async runtime needed.
+use crate::error::{Error, Result};
+use crate::net_extra;
+use crate::protocol::{QtvusersQuery, Query, StatusQuery};
+use crate::server::QuakeServer;
+use crate::status_flags::StatusFlags;
+use crate::svc_qtvusers::QtvusersResponse;
+use crate::svc_status::{Status119Response, StatusResponse};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Resolve an address, IPv4 addresses are preferred.
+pub fn resolve(address: &str) -> Result<SocketAddr> {
+    let addrs: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .map_err(|_| Error::Resolve(address.to_string()))?
+        .collect();
+    net_extra::preferred(&addrs).ok_or(Error::Resolve(address.to_string()))
+}
+
+/// Send a message to the specified address and read the response.
+pub fn send_and_receive(address: &str, message: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+    send_and_receive_to(resolve(address)?, message, timeout)
+}
+
+fn send_and_receive_to(
+    socket_address: SocketAddr,
+    message: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>> {
+    // a zero read timeout means blocking forever
+    if timeout.is_zero() {
+        return Err(Error::Timeout);
+    }
+
+    let ip = match socket_address {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(ip, 0))?;
+
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(socket_address)?;
+    socket.send(message)?;
+
+    let mut buffer = vec![0; 64 * 1024]; // 64 kb
+    match socket.recv(&mut buffer) {
+        Ok(bytes_read) => Ok(buffer[..bytes_read].to_vec()),
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Err(Error::Timeout)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Send a query and decode the response.
+pub fn query<Q: Query>(address: &str, query: &Q, timeout: Duration) -> Result<Q::Response> {
+    let bytes = send_and_receive(address, &query.encode(), timeout)?;
+    query.decode(&bytes)
+}
+
+pub fn status(address: &str, flags: StatusFlags, timeout: Duration) -> Result<StatusResponse> {
+    Ok(query(address, &StatusQuery::new(flags), timeout)?.value)
+}
+
+pub fn status_119(address: &str, timeout: Duration) -> Result<Status119Response> {
+    status(address, StatusFlags::FULL, timeout)
+}
+
+pub fn qtvusers(address: &str, timeout: Duration) -> Result<QtvusersResponse> {
+    query(address, &QtvusersQuery, timeout)
+}
+
+impl QuakeServer {
+    /// Blocking version of `QuakeServer::try_from_address`.
+    pub fn query_blocking(address: &str, timeout: Duration) -> Result<Self> {
+        let resolved = resolve(address)?;
+        let socket_address = resolved.to_string();
+
+        let start = Instant::now();
+        let res = status_119(&socket_address, timeout)?;
+        let latency = start.elapsed();
+
+        let qtvusers = match res.qtv_stream {
+            Some(_) => qtvusers(&socket_address, timeout).ok(),
+            None => None,
+        };
+        Self::try_from_responses(address, resolved, res, qtvusers, Some(latency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server_type::ServerType;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::thread;
+
+    #[test]
+    fn test_query_blocking() -> Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0")?;
+        let address = server.local_addr()?.to_string();
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            while let Ok((len, from)) = server.recv_from(&mut buf) {
+                let response: &[u8] = match &buf[..len] {
+                    b"\xff\xff\xff\xffstatus 119" => b"\xff\xff\xff\xffn\\hostname\\foo\\*version\\MVDSV 0.36\n63 43 41 25 \"XantoM\" \"\" 4 4 \"red\" \"\"\nqtv 1 \"foo Qtv (1)\" \"1@127.0.0.1:28000\" 1\n",
+                    b"\xff\xff\xff\xffqtvusers" => b"\xff\xff\xff\xffnqtvusers 1 \"bar\"\n",
+                    _ => continue,
+                };
+                let _ = server.send_to(response, from);
+            }
+        });
+
+        let server = QuakeServer::query_blocking(&address, Duration::from_millis(200))?;
+        assert_eq!(server.server_type, ServerType::GameServer);
+        assert_eq!(server.settings.hostname, Some("foo".to_string()));
+        assert_eq!(server.clients.len(), 1);
+        assert_eq!(
+            server.qtv_stream.map(|s| s.client_names),
+            Some(vec!["bar".to_string()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout() -> Result<()> {
+        let silent = UdpSocket::bind("127.0.0.1:0")?;
+        let address = silent.local_addr()?.to_string();
+        let res = status_119(&address, Duration::from_millis(20));
+        assert!(matches!(res, Err(Error::Timeout)));
+        Ok(())
+    }
+}
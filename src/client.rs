@@ -1,7 +1,9 @@
+use crate::error::{Error, Result, parse_field};
+use crate::status_flags::StatusFlags;
 use crate::tokenize;
-use anyhow::Result;
 use quake_text::{bytestr, unicode};
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 
 #[cfg(feature = "json")]
@@ -27,30 +29,45 @@ pub struct QuakeClient {
     pub is_bot: bool,
 }
 
-impl TryFrom<&[u8]> for QuakeClient {
-    type Error = anyhow::Error;
-
-    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        let parts: Vec<String> = tokenize::tokenize(bytestr::to_unicode(bytes).as_str());
-        let id: u32 = parts[0].parse()?;
-        let mut frags: i32 = parts[1].parse()?;
-        let time: u32 = parts[2].parse()?;
-        let ping_: i32 = parts[3].parse()?;
+impl QuakeClient {
+    /// Parse a client row of a status response requested with the given flags.
+    pub fn try_from_status_row(bytes: &[u8], flags: StatusFlags) -> Result<Self> {
+        let row = bytestr::to_unicode(bytes);
+        let parts: Vec<Cow<str>> = tokenize::tokens(row.as_str()).collect();
+
+        // id frags time ping name skin top_color bottom_color [team] [auth cc]
+        const MIN_COLUMNS: usize = 8;
+
+        if parts.len() < MIN_COLUMNS {
+            return Err(Error::parse("client", &row));
+        }
+
+        let id: u32 = parse_field(&parts[0], "id", &row)?;
+        let time: u32 = parse_field(&parts[2], "time", &row)?;
+        let ping_: i32 = parse_field(&parts[3], "ping", &row)?;
         let mut name = parts[4].to_string();
         let skin = parts[5].to_string();
-        let top_color: u8 = parts[6].parse()?;
-        let bottom_color: u8 = parts[7].parse()?;
-        let team = match parts.len() >= 9 {
-            true => parts[8].to_string(),
-            _ => "".to_string(),
+        let top_color: u8 = parse_field(&parts[6], "top color", &row)?;
+        let bottom_color: u8 = parse_field(&parts[7], "bottom color", &row)?;
+
+        // optional columns, in order: team, auth cc
+        let mut extra = parts.iter().skip(8);
+        let team = match flags.contains(StatusFlags::SHOWTEAMS) {
+            true => extra.next().map(|s| s.to_string()).unwrap_or_default(),
+            false => "".to_string(),
         };
-        let auth_cc = match parts.len() >= 10 {
-            true => parts[9].to_string(),
-            _ => "".to_string(),
+        let auth_cc = match flags.contains(StatusFlags::SHOWFLAGS) {
+            true => extra.next().map(|s| s.to_string()).unwrap_or_default(),
+            false => "".to_string(),
+        };
+
+        // spectators are shown with frags "S" when requested as players (ASE)
+        let is_spectator = parts[1] == "S" || ping_ < 1;
+        let frags: i32 = match is_spectator {
+            true => 0,
+            false => parse_field(&parts[1], "frags", &row)?,
         };
-        let is_spectator = ping_ < 1;
         if is_spectator {
-            frags = 0;
             name = name.trim_start_matches("\\s\\").to_string();
         }
         let ping = ping_.unsigned_abs();
@@ -73,6 +90,14 @@ impl TryFrom<&[u8]> for QuakeClient {
     }
 }
 
+impl TryFrom<&[u8]> for QuakeClient {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::try_from_status_row(bytes, StatusFlags::FULL)
+    }
+}
+
 impl PartialOrd for QuakeClient {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -162,6 +187,22 @@ mod tests {
                 }
             );
         }
+
+        // invalid
+        {
+            for bytes in [b"".as_slice(), b"63 43 41", br#"63 43 41 25 "ToT_Oddjob"#] {
+                assert_eq!(
+                    QuakeClient::try_from(bytes).unwrap_err().to_string(),
+                    "Invalid client"
+                );
+            }
+            assert_eq!(
+                QuakeClient::try_from(br#"63 x 41 25 "ToT_Oddjob" "" 4 4"#.as_slice())
+                    .unwrap_err()
+                    .to_string(),
+                "Invalid frags"
+            );
+        }
         Ok(())
     }
 
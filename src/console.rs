@@ -0,0 +1,427 @@
+//! Parsers for console output of MVDSV/KTX commands, e.g. as returned by rcon.
+use crate::client::QuakeClient;
+use crate::error::{Error, Result, parse_field};
+use quake_serverinfo::Settings;
+use std::collections::HashMap;
+
+/// Output of the `users` command.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct UsersResponse {
+    pub clients: Vec<QuakeClient>,
+}
+
+impl TryFrom<&str> for UsersResponse {
+    type Error = Error;
+
+    fn try_from(text: &str) -> Result<Self> {
+        // userid frags name
+        // ------ ----- ----
+        //    412    23 name
+        let mut lines = text.lines().skip_while(|l| !l.starts_with("userid"));
+
+        if lines.next().is_none() {
+            return Err(Error::InvalidHeader);
+        }
+
+        let clients = lines
+            .filter(|l| !l.starts_with("---") && !l.ends_with("total users"))
+            .filter_map(|l| users_row(l).ok())
+            .collect();
+
+        Ok(Self { clients })
+    }
+}
+
+fn users_row(line: &str) -> Result<QuakeClient> {
+    let (line, is_spectator) = strip_spectator_suffix(line);
+    // %6i %5i %s
+    let (id, rest) = line
+        .trim_start()
+        .split_once(' ')
+        .ok_or_else(|| Error::parse("users row", line))?;
+    let (frags, name) = rest
+        .trim_start()
+        .split_once(' ')
+        .ok_or_else(|| Error::parse("users row", line))?;
+
+    Ok(QuakeClient {
+        id: parse_field(id, "id", line)?,
+        name: name.to_string(),
+        frags: parse_field(frags, "frags", line)?,
+        is_spectator,
+        ..Default::default()
+    })
+}
+
+/// A client row of the console `status` command.
+#[derive(Debug, Default, PartialEq)]
+pub struct StatusClient {
+    pub client: QuakeClient,
+    pub address: String,
+    pub rate: Option<u32>,
+    /// Packet loss in percent.
+    pub drop: Option<f32>,
+    /// Set for clients that are not fully connected, e.g. "CONNECTING" or "ZOMBIE".
+    pub state: Option<String>,
+}
+
+/// Output of the `status` command when redirected (e.g. rcon).
+#[derive(Debug, Default, PartialEq)]
+pub struct StatusTextResponse {
+    pub address: Option<String>,
+    pub map: Option<String>,
+    pub clients: Vec<StatusClient>,
+}
+
+impl TryFrom<&str> for StatusTextResponse {
+    type Error = Error;
+
+    fn try_from(text: &str) -> Result<Self> {
+        let lines: Vec<&str> = text.lines().collect();
+        let table_start = lines
+            .iter()
+            .position(|l| l.starts_with("name") && l.contains("userid"))
+            .ok_or(Error::InvalidHeader)?;
+
+        // key : value
+        let info: HashMap<&str, &str> = lines[..table_start]
+            .iter()
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+
+        // the header is followed by the address header and a separator line,
+        // then rows come in pairs of name and address lines
+        let rows: Vec<&str> = lines
+            .get(table_start + 3..)
+            .unwrap_or_default()
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .cloned()
+            .collect();
+
+        let clients = rows
+            .chunks_exact(2)
+            .filter_map(|pair| status_rows(pair[0], pair[1]).ok())
+            .collect();
+
+        Ok(Self {
+            address: info.get("net address").map(|v| v.to_string()),
+            map: info.get("current map").map(|v| v.to_string()),
+            clients,
+        })
+    }
+}
+
+fn status_rows(name_line: &str, address_line: &str) -> Result<StatusClient> {
+    // %-16.16s  %6i %5i[ (s)]
+    let (name_line, is_spectator) = strip_spectator_suffix(name_line);
+    let (rest, frags) = name_line
+        .trim_end()
+        .rsplit_once(' ')
+        .ok_or_else(|| Error::parse("status row", name_line))?;
+    let (name, id) = rest
+        .trim_end()
+        .rsplit_once(' ')
+        .ok_or_else(|| Error::parse("status row", name_line))?;
+    let frags: i32 = parse_field(frags, "frags", name_line)?;
+    let id: u32 = parse_field(id, "id", name_line)?;
+    let name = name.trim_end().to_string();
+
+    //   %-16.16s %4i %4i %5.2f, or a connection state
+    let parts: Vec<&str> = address_line.split_whitespace().collect();
+    let address = parts
+        .first()
+        .ok_or_else(|| Error::parse("status row", address_line))?
+        .to_string();
+    let (rate, ping, drop, state) = match parts.as_slice() {
+        [_, rate, ping, drop] => (
+            Some(parse_field(rate, "rate", address_line)?),
+            parse_field(ping, "ping", address_line)?,
+            Some(parse_field(drop, "drop", address_line)?),
+            None,
+        ),
+        [_, state] => (None, 0, None, Some(state.to_string())),
+        _ => return Err(Error::parse("status row", address_line)),
+    };
+
+    Ok(StatusClient {
+        client: QuakeClient {
+            id,
+            name,
+            frags,
+            ping,
+            is_spectator,
+            ..Default::default()
+        },
+        address,
+        rate,
+        drop,
+        state,
+    })
+}
+
+/// Output of the `serverinfo` command.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ServerinfoResponse {
+    pub settings: Settings,
+}
+
+impl TryFrom<&str> for ServerinfoResponse {
+    type Error = Error;
+
+    fn try_from(text: &str) -> Result<Self> {
+        // keys are padded to 20 columns
+        let info: HashMap<String, String> = text
+            .lines()
+            .filter(|l| !l.starts_with("Server info settings"))
+            .filter_map(|l| l.split_once(char::is_whitespace))
+            .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+            .collect();
+
+        if info.is_empty() {
+            return Err(Error::InvalidBody);
+        }
+
+        Ok(Self {
+            settings: Settings::from(&info),
+        })
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct LastScore {
+    pub mode: String,
+    pub map: String,
+    pub time: String,
+    pub entries: Vec<(String, i32)>,
+}
+
+/// Output of the KTX `lastscores` command.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct LastscoresResponse {
+    pub games: Vec<LastScore>,
+}
+
+impl TryFrom<&str> for LastscoresResponse {
+    type Error = Error;
+
+    fn try_from(text: &str) -> Result<Self> {
+        if text.trim_start().starts_with("Lastscores data empty") {
+            return Ok(Self::default());
+        }
+
+        let mut lines = text.lines().skip_while(|l| !l.starts_with("Lastscores"));
+
+        if lines.next().is_none() {
+            return Err(Error::InvalidHeader);
+        }
+
+        let games = lines.filter_map(|l| lastscores_row(l).ok()).collect();
+        Ok(Self { games })
+    }
+}
+
+fn lastscores_row(line: &str) -> Result<LastScore> {
+    // n: mode map time name1 [score1] vs [score2] name2
+    let (_, game) = line
+        .split_once(':')
+        .ok_or_else(|| Error::parse("lastscores row", line))?;
+    let (left, right) = game
+        .split_once(" vs ")
+        .ok_or_else(|| Error::parse("lastscores row", line))?;
+
+    let mut parts = left.split_whitespace();
+    let (Some(mode), Some(map), Some(time)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(Error::parse("lastscores row", line));
+    };
+    let left: Vec<&str> = parts.collect();
+    let right: Vec<&str> = right.split_whitespace().collect();
+
+    let (Some((score1, name1)), Some((score2, name2))) = (left.split_last(), right.split_first())
+    else {
+        return Err(Error::parse("lastscores row", line));
+    };
+
+    Ok(LastScore {
+        mode: mode.to_string(),
+        map: map.to_string(),
+        time: time.to_string(),
+        entries: vec![
+            (name1.join(" "), parse_score(score1, line)?),
+            (name2.join(" "), parse_score(score2, line)?),
+        ],
+    })
+}
+
+fn parse_score(value: &str, row: &str) -> Result<i32> {
+    let value = value.trim_matches(|c| c == '[' || c == ']').trim();
+    parse_field(value, "score", row)
+}
+
+fn strip_spectator_suffix(line: &str) -> (&str, bool) {
+    match line.trim_end().strip_suffix(" (s)") {
+        Some(line) => (line, true),
+        None => (line, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_users() -> Result<()> {
+        assert_eq!(
+            UsersResponse::try_from("foo").unwrap_err().to_string(),
+            "Invalid header"
+        );
+
+        let res = UsersResponse::try_from(include_str!("../tests/fixtures/console_users.txt"))?;
+        assert_eq!(
+            res.clients,
+            vec![
+                QuakeClient {
+                    id: 412,
+                    name: "ToT_Oddjob".to_string(),
+                    frags: 23,
+                    ..Default::default()
+                },
+                QuakeClient {
+                    id: 415,
+                    name: "bps".to_string(),
+                    frags: -1,
+                    ..Default::default()
+                },
+                QuakeClient {
+                    id: 418,
+                    name: "razor".to_string(),
+                    frags: 0,
+                    is_spectator: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_status() -> Result<()> {
+        assert_eq!(
+            StatusTextResponse::try_from("foo").unwrap_err().to_string(),
+            "Invalid header"
+        );
+
+        let res =
+            StatusTextResponse::try_from(include_str!("../tests/fixtures/console_status.txt"))?;
+        assert_eq!(res.address, Some("192.168.1.10:28501".to_string()));
+        assert_eq!(res.map, Some("dm3".to_string()));
+        assert_eq!(
+            res.clients,
+            vec![
+                StatusClient {
+                    client: QuakeClient {
+                        id: 412,
+                        name: "ToT_Oddjob".to_string(),
+                        frags: 23,
+                        ping: 25,
+                        ..Default::default()
+                    },
+                    address: "83.250.21.14".to_string(),
+                    rate: Some(77),
+                    drop: Some(0.0),
+                    state: None,
+                },
+                StatusClient {
+                    client: QuakeClient {
+                        id: 415,
+                        name: "player with long".to_string(),
+                        frags: -1,
+                        ping: 12,
+                        ..Default::default()
+                    },
+                    address: "10.0.0.2".to_string(),
+                    rate: Some(77),
+                    drop: Some(1.25),
+                    state: None,
+                },
+                StatusClient {
+                    client: QuakeClient {
+                        id: 418,
+                        name: "razor".to_string(),
+                        is_spectator: true,
+                        ..Default::default()
+                    },
+                    address: "172.16.0.3".to_string(),
+                    rate: None,
+                    drop: None,
+                    state: Some("CONNECTING".to_string()),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_separator_like_name() -> Result<()> {
+        let text = "\
+name               userid frags
+  address          rate ping drop
+  ---------------- ---- ---- -----
+---=bps=---          416     5
+  10.0.0.4         77   20  0.00
+razor                418     0
+  172.16.0.3       77   30  0.50
+";
+        let res = StatusTextResponse::try_from(text)?;
+        let names: Vec<&str> = res.clients.iter().map(|c| c.client.name.as_str()).collect();
+        assert_eq!(names, vec!["---=bps=---", "razor"]);
+        assert_eq!(res.clients[0].address, "10.0.0.4");
+        assert_eq!(res.clients[1].client.ping, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn test_serverinfo() -> Result<()> {
+        assert_eq!(
+            ServerinfoResponse::try_from("").unwrap_err().to_string(),
+            "Invalid body"
+        );
+
+        let res =
+            ServerinfoResponse::try_from(include_str!("../tests/fixtures/console_serverinfo.txt"))?;
+        assert_eq!(
+            res.settings.hostname,
+            Some("QUAKE.SE KTX:28501".to_string())
+        );
+        assert_eq!(res.settings.map, Some("dm3".to_string()));
+        assert_eq!(res.settings.maxclients, Some(8));
+        assert_eq!(res.settings.timelimit, Some(10));
+        assert_eq!(res.settings.teamplay, Some(2));
+        assert_eq!(res.settings.version, Some("MVDSV 0.36".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lastscores() -> Result<()> {
+        assert_eq!(
+            LastscoresResponse::try_from("Lastscores data empty\n")?,
+            LastscoresResponse::default()
+        );
+
+        let res =
+            LastscoresResponse::try_from(include_str!("../tests/fixtures/console_lastscores.txt"))?;
+        assert_eq!(res.games.len(), 3);
+        assert_eq!(
+            res.games[1],
+            LastScore {
+                mode: "2on2".to_string(),
+                map: "dm3".to_string(),
+                time: "20:55".to_string(),
+                entries: vec![("red".to_string(), 120), ("blue".to_string(), 98)],
+            }
+        );
+        Ok(())
+    }
+}
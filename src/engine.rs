@@ -0,0 +1,462 @@
+use crate::error::{Error, Result};
+use crate::net_extra;
+use crate::rt::{self, OneshotSender, UdpSocket};
+use crate::server::QuakeServer;
+use crate::status_flags::StatusFlags;
+use crate::svc_qtvusers::QtvusersResponse;
+use crate::svc_status::{Status119Response, StatusResponse};
+use crate::transport::{Transport, TransportFuture};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+type PendingMap = HashMap<SocketAddr, VecDeque<PendingRequest>>;
+type Pending = Arc<Mutex<PendingMap>>;
+
+/// Max time until a receive loop notices that its engine was dropped.
+const RECEIVE_TICK: Duration = Duration::from_millis(100);
+
+struct PendingRequest {
+    id: u64,
+    kind: MessageKind,
+    tx: OneshotSender<Vec<u8>>,
+}
+
+/// Type of a request or response, replies are only routed to requests of the
+/// same kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MessageKind {
+    Status,
+    Qtvusers,
+    Challenge,
+    Ping,
+    Unknown,
+}
+
+impl MessageKind {
+    fn of_request(message: &[u8]) -> Self {
+        match message.strip_prefix(b"\xff\xff\xff\xff") {
+            Some(m) if m.starts_with(b"status") => Self::Status,
+            Some(m) if m.starts_with(b"qtvusers") => Self::Qtvusers,
+            Some(m) if m.starts_with(b"getchallenge") => Self::Challenge,
+            Some(b"k") => Self::Ping,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn of_response(bytes: &[u8]) -> Self {
+        match bytes.strip_prefix(b"\xff\xff\xff\xff") {
+            Some(b) if b.starts_with(b"nqtvusers") => Self::Qtvusers,
+            Some(b) if b.starts_with(b"n") => Self::Status,
+            Some(b) if b.starts_with(b"c") => Self::Challenge,
+            Some(b) if b.starts_with(b"l") => Self::Ping,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Requests of unknown kind accept any reply.
+    fn accepts(&self, response: MessageKind) -> bool {
+        *self == Self::Unknown || *self == response
+    }
+}
+
+/// Sends all queries from a single UDP socket per address family.
+///
+/// The socket of the other address family is bound on first use, i.e. IPv4
+/// and IPv6 servers can be queried using the same engine.
+///
+/// Replies are routed back to the pending request by source address and kind
+/// (e.g. a `status` reply is never handed to a `qtvusers` request). If several
+/// requests of the same kind to the same address are in flight, replies are
+/// handed out in the order the requests were sent. Replies without a matching
+/// request, e.g. arriving after a timeout, are dropped.
+pub struct QueryEngine {
+    v4: Mutex<Option<Arc<UdpSocket>>>,
+    v6: Mutex<Option<Arc<UdpSocket>>>,
+    pending: Pending,
+    next_id: AtomicU64,
+}
+
+impl QueryEngine {
+    /// Bind an IPv4 socket, the IPv6 socket is bound on first use.
+    pub async fn bind() -> Result<Self> {
+        Self::bind_to(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)).await
+    }
+
+    /// Bind an IPv6 socket, the IPv4 socket is bound on first use.
+    pub async fn bind_v6() -> Result<Self> {
+        Self::bind_to(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0)).await
+    }
+
+    /// Bind the socket of the address family of `address`, the other one is
+    /// bound on first use.
+    pub async fn bind_to(address: SocketAddr) -> Result<Self> {
+        let engine = Self {
+            v4: Mutex::default(),
+            v6: Mutex::default(),
+            pending: Pending::default(),
+            next_id: AtomicU64::new(0),
+        };
+        engine.bind_socket(address).await?;
+        Ok(engine)
+    }
+
+    /// Address of the IPv4 socket, or the IPv6 socket if only that one is bound.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        let socket = lock(&self.v4)?.clone().or(lock(&self.v6)?.clone());
+        match socket {
+            Some(socket) => Ok(socket.local_addr()?),
+            None => Err(Error::Io(io::Error::other("Not bound"))),
+        }
+    }
+
+    /// Socket of the address family of `target`, bound if needed.
+    async fn socket_for(&self, target: &SocketAddr) -> Result<Arc<UdpSocket>> {
+        if let Some(socket) = lock(self.slot(target))?.clone() {
+            return Ok(socket);
+        }
+
+        let ip = match target {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        self.bind_socket(SocketAddr::new(ip, 0)).await
+    }
+
+    async fn bind_socket(&self, address: SocketAddr) -> Result<Arc<UdpSocket>> {
+        let socket = Arc::new(UdpSocket::bind(address).await?);
+
+        // another request may have bound a socket in the meantime
+        let mut slot = lock(self.slot(&address))?;
+        if let Some(socket) = slot.as_ref() {
+            return Ok(socket.clone());
+        }
+        *slot = Some(socket.clone());
+
+        rt::spawn(receive_loop(socket.clone(), Arc::downgrade(&self.pending)));
+        Ok(socket)
+    }
+
+    fn slot(&self, address: &SocketAddr) -> &Mutex<Option<Arc<UdpSocket>>> {
+        match address {
+            SocketAddr::V4(_) => &self.v4,
+            SocketAddr::V6(_) => &self.v6,
+        }
+    }
+
+    pub async fn send_and_receive(
+        &self,
+        address: SocketAddr,
+        message: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        let socket = self.socket_for(&address).await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = rt::oneshot();
+        lock(&self.pending)?
+            .entry(address)
+            .or_default()
+            .push_back(PendingRequest {
+                id,
+                kind: MessageKind::of_request(message),
+                tx,
+            });
+
+        if let Err(err) = socket.send_to(message, address).await {
+            self.remove_pending(address, id);
+            return Err(err.into());
+        }
+
+        match rt::timeout(timeout, rx.recv()).await {
+            Ok(Some(bytes)) => Ok(bytes),
+            Ok(None) => Err(Error::Io(io::Error::other("Receiver stopped"))),
+            Err(_) => {
+                self.remove_pending(address, id);
+                Err(Error::Timeout)
+            }
+        }
+    }
+
+    pub async fn status(
+        &self,
+        address: &str,
+        flags: StatusFlags,
+        timeout: Duration,
+    ) -> Result<StatusResponse> {
+        let socket_address = self.resolve(address).await?;
+        let bytes = self
+            .send_and_receive(socket_address, &flags.message(), timeout)
+            .await?;
+        StatusResponse::parse(bytes.as_slice(), flags)
+    }
+
+    pub async fn status_119(&self, address: &str, timeout: Duration) -> Result<Status119Response> {
+        self.status(address, StatusFlags::FULL, timeout).await
+    }
+
+    pub async fn qtvusers(&self, address: &str, timeout: Duration) -> Result<QtvusersResponse> {
+        let socket_address = self.resolve(address).await?;
+        let bytes = self
+            .send_and_receive(socket_address, b"\xff\xff\xff\xffqtvusers", timeout)
+            .await?;
+        QtvusersResponse::try_from(bytes.as_slice())
+    }
+
+    pub async fn query(&self, address: &str, timeout: Duration) -> Result<QuakeServer> {
+        let socket_address = self.resolve(address).await?;
+        let start = Instant::now();
+        let bytes = self
+            .send_and_receive(socket_address, &StatusFlags::FULL.message(), timeout)
+            .await?;
+        let latency = start.elapsed();
+        let res = Status119Response::try_from(bytes.as_slice())?;
+        let qtvusers = match res.qtv_stream {
+            Some(_) => self.qtvusers(address, timeout).await.ok(),
+            None => None,
+        };
+        QuakeServer::try_from_responses(address, socket_address, res, qtvusers, Some(latency))
+    }
+
+    async fn resolve(&self, address: &str) -> Result<SocketAddr> {
+        net_extra::resolve(address).await
+    }
+
+    fn remove_pending(&self, address: SocketAddr, id: u64) {
+        if let Ok(mut pending) = self.pending.lock()
+            && let Some(queue) = pending.get_mut(&address)
+        {
+            queue.retain(|r| r.id != id);
+            if queue.is_empty() {
+                pending.remove(&address);
+            }
+        }
+    }
+}
+
+impl Transport for QueryEngine {
+    fn send_and_receive<'a>(
+        &'a self,
+        address: &'a str,
+        message: &'a [u8],
+        timeout: Duration,
+    ) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let socket_address = self.resolve(address).await?;
+            QueryEngine::send_and_receive(self, socket_address, message, timeout).await
+        })
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> Result<std::sync::MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| Error::Io(io::Error::other("Lock poisoned")))
+}
+
+/// Hand out replies to pending requests, until the engine is dropped.
+async fn receive_loop(socket: Arc<UdpSocket>, pending: Weak<Mutex<PendingMap>>) {
+    let mut buffer = vec![0; 64 * 1024]; // 64 kb
+
+    loop {
+        let res = rt::timeout(RECEIVE_TICK, socket.recv_from(&mut buffer)).await;
+        let Some(pending) = pending.upgrade() else {
+            return;
+        };
+        let Ok(Ok((bytes_read, from))) = res else {
+            continue;
+        };
+
+        let bytes = &buffer[..bytes_read];
+        let kind = MessageKind::of_response(bytes);
+
+        let request = match pending.lock() {
+            Ok(mut pending) => {
+                let request = pending.get_mut(&from).and_then(|queue| {
+                    let index = queue.iter().position(|r| r.kind.accepts(kind))?;
+                    queue.remove(index)
+                });
+                if pending.get(&from).is_some_and(|q| q.is_empty()) {
+                    pending.remove(&from);
+                }
+                request
+            }
+            Err(_) => return,
+        };
+
+        if let Some(request) = request {
+            request.tx.send(bytes.to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockServer;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_send_and_receive() -> Result<()> {
+        let engine = QueryEngine::bind_to("127.0.0.1:0".parse()?).await?;
+
+        // two echo servers, replying in reverse order
+        let server_a = UdpSocket::bind("127.0.0.1:0").await?;
+        let server_b = UdpSocket::bind("127.0.0.1:0").await?;
+        let address_a = server_a.local_addr()?;
+        let address_b = server_b.local_addr()?;
+
+        let echo = tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let (len_a, from_a) = server_a.recv_from(&mut buf).await?;
+            let msg_a = buf[..len_a].to_vec();
+            let (len_b, from_b) = server_b.recv_from(&mut buf).await?;
+            server_b.send_to(&buf[..len_b], from_b).await?;
+            server_a.send_to(&msg_a, from_a).await?;
+            anyhow::Ok(())
+        });
+
+        let timeout = Duration::from_millis(500);
+        let (res_a, res_b) =
+            tokio::join!(engine.send_and_receive(address_a, b"a", timeout), async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                engine.send_and_receive(address_b, b"b", timeout).await
+            });
+        echo.await??;
+
+        assert_eq!(res_a?, b"a".to_vec());
+        assert_eq!(res_b?, b"b".to_vec());
+        assert!(engine.pending.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout() -> Result<()> {
+        let engine = QueryEngine::bind_to("127.0.0.1:0".parse()?).await?;
+        let silent = UdpSocket::bind("127.0.0.1:0").await?;
+        let res = engine
+            .send_and_receive(silent.local_addr()?, b"a", Duration::from_millis(20))
+            .await;
+
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Timeout reached while waiting for response"
+        );
+        assert!(engine.pending.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_both_address_families() -> Result<()> {
+        let Ok(server_v6) = UdpSocket::bind("[::1]:0").await else {
+            return Ok(()); // no IPv6 support
+        };
+        let engine = QueryEngine::bind().await?;
+        let server_v4 = UdpSocket::bind("127.0.0.1:0").await?;
+        let address_v4 = server_v4.local_addr()?;
+        let address_v6 = server_v6.local_addr()?;
+
+        let echo = tokio::spawn(async move {
+            let mut buf = [0; 64];
+            for server in [server_v4, server_v6] {
+                let (len, from) = server.recv_from(&mut buf).await?;
+                server.send_to(&buf[..len], from).await?;
+            }
+            anyhow::Ok(())
+        });
+
+        let timeout = Duration::from_millis(500);
+        let res_v4 = engine.send_and_receive(address_v4, b"a", timeout).await?;
+        let res_v6 = engine.send_and_receive(address_v6, b"b", timeout).await?;
+        echo.await??;
+
+        assert_eq!(res_v4, b"a".to_vec());
+        assert_eq!(res_v6, b"b".to_vec());
+        assert!(engine.local_addr()?.is_ipv4());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_drop() -> Result<()> {
+        let engine = QueryEngine::bind_to("127.0.0.1:0".parse()?).await?;
+        let address = engine.local_addr()?;
+        drop(engine);
+
+        // the receive loop stops and releases the socket
+        tokio::time::sleep(RECEIVE_TICK * 2).await;
+        assert!(UdpSocket::bind(address).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_late_reply() -> Result<()> {
+        let engine = QueryEngine::bind_to("127.0.0.1:0".parse()?).await?;
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?;
+        let timeout = Duration::from_millis(50);
+
+        let res = engine
+            .send_and_receive(address, b"\xff\xff\xff\xffstatus 119", timeout)
+            .await;
+        assert!(matches!(res, Err(Error::Timeout)));
+
+        // reply to the timed out status request arrives before the qtvusers reply
+        let responder = tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let (_, from) = server.recv_from(&mut buf).await?;
+            server
+                .send_to(b"\xff\xff\xff\xffn\\hostname\\foo\n", from)
+                .await?;
+            server
+                .send_to(b"\xff\xff\xff\xffnqtvusers 1 \"bar\"\n", from)
+                .await?;
+            anyhow::Ok(())
+        });
+        let res = engine
+            .send_and_receive(address, b"\xff\xff\xff\xffqtvusers", timeout)
+            .await?;
+        responder.await??;
+
+        assert_eq!(res, b"\xff\xff\xff\xffnqtvusers 1 \"bar\"\n".to_vec());
+        assert!(engine.pending.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_kind() {
+        assert_eq!(
+            MessageKind::of_request(b"\xff\xff\xff\xffstatus 119"),
+            MessageKind::Status
+        );
+        assert_eq!(
+            MessageKind::of_response(b"\xff\xff\xff\xffnqtvusers 1\n"),
+            MessageKind::Qtvusers
+        );
+        assert_eq!(
+            MessageKind::of_response(b"\xff\xff\xff\xffn\\hostname\\foo\n"),
+            MessageKind::Status
+        );
+        assert!(!MessageKind::Qtvusers.accepts(MessageKind::Status));
+        assert!(MessageKind::Unknown.accepts(MessageKind::Status));
+    }
+
+    #[tokio::test]
+    async fn test_query() -> Result<()> {
+        let mock = MockServer::mvdsv("QUAKE.SE KTX:28501").spawn()?;
+        let engine = QueryEngine::bind().await?;
+        let server = engine
+            .query(&mock.address(), Duration::from_secs_f32(0.5))
+            .await?;
+        assert!(
+            server
+                .settings
+                .hostname
+                .unwrap()
+                .starts_with("QUAKE.SE KTX:28501")
+        );
+        Ok(())
+    }
+}
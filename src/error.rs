@@ -0,0 +1,77 @@
+//! Errors returned by queries and response parsers.
+//!
+//! Functions returning `anyhow::Result` wrap these errors, use
+//! `err.downcast_ref::<Error>()` to inspect them.
+use std::str::FromStr;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No response within the given timeout.
+    #[error("Timeout reached while waiting for response")]
+    Timeout,
+
+    /// The address could not be resolved.
+    #[error("Unable to resolve address {0}")]
+    Resolve(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The response is not of the expected type.
+    #[error("Invalid header")]
+    InvalidHeader,
+
+    /// The response is of the expected type but its content is malformed.
+    #[error("Invalid body")]
+    InvalidBody,
+
+    /// The response ended before all expected values were read.
+    #[error("Unexpected end of data")]
+    UnexpectedEnd,
+
+    /// A value could not be parsed, `row` is the row (or value) it was read from.
+    #[error("Invalid {field}")]
+    Parse { field: &'static str, row: String },
+
+    /// The server rejected the rcon password.
+    #[error("Bad rcon password")]
+    BadRconPassword,
+
+    /// Error message sent by the server, e.g. a QTV proxy refusing a request.
+    #[error("{0}")]
+    Server(String),
+}
+
+impl Error {
+    pub(crate) fn parse(field: &'static str, row: &str) -> Self {
+        Self::Parse {
+            field,
+            row: row.to_string(),
+        }
+    }
+}
+
+/// Parse a value, failing with [`Error::Parse`].
+pub(crate) fn parse_field<T: FromStr>(value: &str, field: &'static str, row: &str) -> Result<T> {
+    value.parse().map_err(|_| Error::parse(field, row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_field() {
+        assert_eq!(parse_field::<u32>("12", "id", "12 S").unwrap(), 12);
+
+        let err = parse_field::<u32>("S", "frags", "12 S").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid frags");
+        assert!(matches!(
+            err,
+            Error::Parse { field: "frags", row } if row == "12 S"
+        ));
+    }
+}
@@ -19,12 +19,16 @@ pub struct GameServer {
     pub qtv_stream: Option<QtvStream>,
 }
 
-impl From<&QuakeServer> for GameServer {
-    fn from(server: &QuakeServer) -> Self {
-        let mut clients = server.clients.clone();
+impl GameServer {
+    pub fn new(
+        settings: &Settings,
+        clients: &[QuakeClient],
+        qtv_stream: Option<QtvStream>,
+    ) -> Self {
+        let mut clients = clients.to_vec();
         clients.sort();
 
-        let is_teamplay = server.settings.teamplay.is_some_and(|tp| tp > 0);
+        let is_teamplay = settings.teamplay.is_some_and(|tp| tp > 0);
 
         let mut players: Vec<Player> = clients
             .iter()
@@ -48,15 +52,21 @@ impl From<&QuakeServer> for GameServer {
         };
 
         Self {
-            settings: server.settings.clone(),
+            settings: settings.clone(),
             teams,
             players,
             spectators,
-            qtv_stream: server.qtv_stream.clone(),
+            qtv_stream,
         }
     }
 }
 
+impl From<&QuakeServer> for GameServer {
+    fn from(server: &QuakeServer) -> Self {
+        Self::new(&server.settings, &server.clients, server.qtv_stream.clone())
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
 pub struct Player {
@@ -114,20 +124,34 @@ impl From<&QuakeClient> for Spectator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anyhow::Result;
-    use std::time::Duration;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use {crate::mock::MockServer, anyhow::Result, std::time::Duration};
 
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
     #[tokio::test]
     async fn test_from_gameserver() -> Result<()> {
+        let mock = MockServer::mvdsv("QUAKE.SE KTX:28501 (duel)")
+            .with_clients(vec![QuakeClient {
+                id: 7,
+                name: "XantoM".to_string(),
+                team: "f0m".to_string(),
+                frags: 12,
+                ping: 25,
+                ..Default::default()
+            }])
+            .spawn()?;
         let server =
-            QuakeServer::try_from_address("quake.se:28501", Duration::from_secs_f32(0.5)).await?;
+            QuakeServer::try_from_address(&mock.address(), Duration::from_secs_f32(0.5)).await?;
+        let gameserver = GameServer::from(&server);
         assert!(
-            GameServer::from(&server)
+            gameserver
                 .settings
                 .hostname
                 .unwrap()
                 .starts_with("QUAKE.SE KTX:28501"),
         );
+        assert_eq!(gameserver.players.len(), 1);
+        assert_eq!(gameserver.players[0].name, "XantoM");
         Ok(())
     }
 
@@ -20,9 +20,12 @@ impl TryFrom<&str> for Hostport {
     type Error = anyhow::Error;
 
     fn try_from(address: &str) -> Result<Self, Self::Error> {
-        let (host, port_str) = address
-            .split_once(':')
-            .ok_or_else(|| e!("Invalid hostport format, expected host:port"))?;
+        // ipv6 literals are enclosed in brackets, e.g. [2001:db8::1]:27500
+        let (host, port_str) = match address.strip_prefix('[') {
+            Some(rest) => rest.split_once("]:"),
+            None => address.split_once(':'),
+        }
+        .ok_or_else(|| e!("Invalid hostport format, expected host:port"))?;
         Ok(Self {
             host: host.to_string(),
             port: port_str.parse::<u16>()?,
@@ -32,7 +35,10 @@ impl TryFrom<&str> for Hostport {
 
 impl Display for Hostport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}", self.host, self.port)
+        match self.host.contains(':') {
+            true => write!(f, "[{}]:{}", self.host, self.port),
+            false => write!(f, "{}:{}", self.host, self.port),
+        }
     }
 }
 
@@ -71,6 +77,16 @@ mod tests {
                 port: 28501,
             }
         });
+        assert_eq!(
+            Hostport::try_from("[2001:db8::1]:27500")?,
+            Hostport {
+                host: "2001:db8::1".to_string(),
+                port: 27500,
+            }
+        );
+        assert!(Hostport::try_from("quake.se").is_err());
+        assert!(Hostport::try_from("[2001:db8::1]").is_err());
+        assert!(Hostport::try_from("2001:db8::1").is_err());
         Ok(())
     }
 
@@ -81,8 +97,12 @@ mod tests {
             port: 28501,
         };
         assert_eq!(hostport.to_string(), "quake.se:28501");
+
+        let hostport = Hostport::new("2001:db8::1".to_string(), 27500);
+        assert_eq!(hostport.to_string(), "[2001:db8::1]:27500");
     }
 
+    #[cfg(feature = "json")]
     #[test]
     fn test_serialize() -> Result<()> {
         let hostport = Hostport {
@@ -96,6 +116,7 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "json")]
     #[test]
     fn test_deserialize() -> Result<()> {
         assert_eq!(
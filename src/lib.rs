@@ -1,16 +1,57 @@
 //! # serverstat
 //! Get information from QuakeWorld servers
+//!
+//! ## Features
+//! - `tokio` (default), `smol`, `async-std`: async runtime used by the async
+//!   APIs (queries, `engine`, `batch`, ...). Without one of them only the
+//!   parsers and, with `blocking`, the `blocking` queries are available.
+//! - `blocking`: queries using [`std::net::UdpSocket`], no async runtime needed.
+//! - `json`: serialize servers and clients using serde.
+//! - `http`: QTV web interface, enables `tokio` (its requests must run within
+//!   a tokio runtime). If `smol` or `async-std` is enabled too, the other async
+//!   APIs still use async-io and work with any executor.
+//! - `mock`: local mock server for tests.
 
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
+pub mod console;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub mod engine;
+pub mod error;
 pub mod gameserver;
 pub mod hostport;
+pub mod master;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod mvd;
 pub mod net_extra;
+pub mod nq;
+pub mod ping;
+pub mod protocol;
+pub mod protocol_extensions;
+pub mod q2;
 pub mod qtv;
+#[cfg(feature = "http")]
+pub mod qtv_http;
+pub mod qtv_tcp;
 pub mod qwfwd;
+pub mod rcon;
+mod reader;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub mod resolver;
+pub mod retry;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+mod rt;
 pub mod server;
 pub mod server_type;
 pub mod software_type;
+pub mod status_flags;
+pub mod svc_challenge;
 pub mod svc_qtvusers;
 pub mod svc_status;
 pub mod team;
 pub mod tokenize;
+pub mod transport;
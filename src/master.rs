@@ -0,0 +1,216 @@
+use crate::error::{Error, Result};
+use crate::hostport::Hostport;
+use std::net::Ipv4Addr;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::protocol::MasterQuery,
+    crate::rt,
+    crate::transport::{self, UdpTransport},
+    std::io,
+    std::time::Duration,
+};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn server_list(address: &str, timeout: Duration) -> Result<Vec<Hostport>> {
+    let response = transport::query(&UdpTransport, address, &MasterQuery, timeout).await?;
+    Ok(response.servers)
+}
+
+/// Query several master servers and merge their server lists.
+///
+/// Masters that fail to respond are ignored, an error is only returned if
+/// none of them responded.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn server_list_from_masters(
+    addresses: &[&str],
+    timeout: Duration,
+) -> Result<Vec<Hostport>> {
+    let results = rt::join_all(
+        addresses
+            .iter()
+            .map(|address| server_list(address, timeout)),
+    )
+    .await;
+
+    let mut lists: Vec<Vec<Hostport>> = vec![];
+    let mut last_error: Option<Error> = None;
+
+    for res in results {
+        match res {
+            Ok(servers) => lists.push(servers),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    if lists.is_empty() {
+        return Err(last_error.unwrap_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No master servers given",
+            ))
+        }));
+    }
+
+    Ok(merge(&lists))
+}
+
+/// Merge server lists into a single sorted list without duplicates.
+pub fn merge(lists: &[Vec<Hostport>]) -> Vec<Hostport> {
+    let mut servers: Vec<Hostport> = lists.iter().flatten().cloned().collect();
+    servers.sort();
+    servers.dedup();
+    servers
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct MasterResponse {
+    pub servers: Vec<Hostport>,
+}
+
+impl TryFrom<&[u8]> for MasterResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        // validate header
+        let header = b"\xff\xff\xff\xffd\n".to_vec();
+
+        if !bytes.starts_with(&header) {
+            return Err(Error::InvalidHeader);
+        }
+
+        // parse body: 4 bytes ip + 2 bytes port (big endian) per server
+        const ENTRY_LENGTH: usize = 6;
+        let body = &bytes[header.len()..];
+
+        if !body.len().is_multiple_of(ENTRY_LENGTH) {
+            return Err(Error::InvalidBody);
+        }
+
+        let servers = body
+            .chunks_exact(ENTRY_LENGTH)
+            .map(|entry| {
+                let ip = Ipv4Addr::new(entry[0], entry[1], entry[2], entry[3]);
+                let port = u16::from_be_bytes([entry[4], entry[5]]);
+                Hostport::new(ip.to_string(), port)
+            })
+            .filter(|hostport| hostport.port > 0)
+            .collect();
+
+        Ok(Self { servers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        // invalid
+        {
+            let res = MasterResponse::try_from([0].as_slice());
+            assert_eq!(res.unwrap_err().to_string(), "Invalid header".to_string());
+        }
+        {
+            let res = MasterResponse::try_from(b"\xff\xff\xff\xffd\n\x01\x02\x03".as_slice());
+            assert_eq!(res.unwrap_err().to_string(), "Invalid body".to_string());
+        }
+
+        // empty
+        {
+            let res = MasterResponse::try_from(b"\xff\xff\xff\xffd\n".as_slice())?;
+            assert_eq!(res.servers, vec![]);
+        }
+
+        // with servers
+        {
+            let bytes = [
+                255, 255, 255, 255, 100, 10, 46, 227, 68, 148, 111, 85, 91, 121, 3, 50, 107, 108,
+                1, 2, 3, 4, 0, 0,
+            ];
+            let res = MasterResponse::try_from(bytes.as_slice())?;
+            assert_eq!(
+                res.servers,
+                vec![
+                    Hostport::new("46.227.68.148".to_string(), 28501),
+                    Hostport::new("91.121.3.50".to_string(), 27500),
+                ]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = Hostport::new("10.0.0.1".to_string(), 27500);
+        let b = Hostport::new("10.0.0.2".to_string(), 27500);
+        let c = Hostport::new("10.0.0.1".to_string(), 28000);
+
+        assert_eq!(
+            merge(&[vec![b.clone(), a.clone()], vec![c.clone(), a.clone()]]),
+            vec![a, c, b]
+        );
+        assert_eq!(merge(&[]), vec![]);
+    }
+
+    /// Master server answering the server list request with `servers`.
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    async fn spawn_master(servers: &[u8]) -> Result<String> {
+        let master = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let address = master.local_addr()?.to_string();
+        let mut response = b"\xff\xff\xff\xffd\n".to_vec();
+        response.extend_from_slice(servers);
+
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            while let Ok((len, from)) = master.recv_from(&mut buf).await {
+                if &buf[..len] == b"c\n" {
+                    let _ = master.send_to(&response, from).await;
+                }
+            }
+        });
+        Ok(address)
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_server_list() -> Result<()> {
+        let address = spawn_master(&[10, 0, 0, 1, 0x6b, 0x6c]).await?;
+        let servers = server_list(&address, Duration::from_millis(200)).await?;
+        assert_eq!(servers, vec![Hostport::new("10.0.0.1".to_string(), 27500)]);
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_server_list_from_masters() -> Result<()> {
+        let live = spawn_master(&[10, 0, 0, 2, 0x6b, 0x6c, 10, 0, 0, 1, 0x6b, 0x6c]).await?;
+        let dead = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let dead = dead.local_addr()?.to_string();
+        let timeout = Duration::from_millis(200);
+
+        assert_eq!(
+            server_list_from_masters(&[dead.as_str(), live.as_str()], timeout).await?,
+            vec![
+                Hostport::new("10.0.0.1".to_string(), 27500),
+                Hostport::new("10.0.0.2".to_string(), 27500),
+            ]
+        );
+        assert!(matches!(
+            server_list_from_masters(&[dead.as_str()], timeout).await,
+            Err(Error::Timeout)
+        ));
+        assert_eq!(
+            server_list_from_masters(&[], timeout)
+                .await
+                .unwrap_err()
+                .to_string(),
+            "No master servers given"
+        );
+        Ok(())
+    }
+}
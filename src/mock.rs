@@ -0,0 +1,386 @@
+//! Local mock server answering like MVDSV, QTV or qwfwd, for offline tests.
+//!
+//! The server runs on a thread with a blocking socket and works with any
+//! async runtime (or none).
+//!
+//! ```
+//! use serverstat::mock::MockServer;
+//!
+//! let handle = MockServer::mvdsv("foo").spawn().unwrap();
+//! let address = handle.address(); // e.g. "127.0.0.1:41234"
+//! ```
+use crate::client::QuakeClient;
+use crate::qtv::QtvStream;
+use crate::status_flags::StatusFlags;
+use quake_serverinfo::Settings;
+use quake_text::unicode;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const HEADER: &[u8] = b"\xff\xff\xff\xff";
+
+/// State of a mock server, see [`MockServer::respond`] for supported requests.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MockServer {
+    pub settings: Settings,
+    pub clients: Vec<QuakeClient>,
+    /// Stream shown with `status` (flag `SHOWQTV`), `client_names` are
+    /// the response to `qtvusers`.
+    pub qtv_stream: Option<QtvStream>,
+}
+
+impl MockServer {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    pub fn mvdsv(hostname: &str) -> Self {
+        Self::with_version(hostname, "MVDSV 0.36")
+    }
+
+    pub fn qtv(hostname: &str) -> Self {
+        Self::with_version(hostname, "QTV 1.12")
+    }
+
+    pub fn qwfwd(hostname: &str) -> Self {
+        Self::with_version(hostname, "qwfwd 1.2")
+    }
+
+    fn with_version(hostname: &str, version: &str) -> Self {
+        Self::new(Settings {
+            hostname: Some(hostname.to_string()),
+            version: Some(version.to_string()),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_clients(self, clients: Vec<QuakeClient>) -> Self {
+        Self { clients, ..self }
+    }
+
+    pub fn with_qtv_stream(self, qtv_stream: QtvStream) -> Self {
+        Self {
+            qtv_stream: Some(qtv_stream),
+            ..self
+        }
+    }
+
+    /// Response to a message, `None` if the server would not respond.
+    ///
+    /// Supported: `status [flags]`, `qtvusers` and A2A ping (`k`).
+    pub fn respond(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let command = message.strip_prefix(HEADER)?;
+        let command = String::from_utf8_lossy(command);
+        let command = command.trim_end();
+
+        let body = match command {
+            "k" => b"l".to_vec(),
+            "qtvusers" => self.qtvusers()?,
+            "status" => self.status(StatusFlags::OLDSTYLE),
+            _ => {
+                let bits = command.strip_prefix("status ")?.parse::<u8>().ok()?;
+                self.status(StatusFlags::from_bits(bits))
+            }
+        };
+
+        Some([HEADER, &body].concat())
+    }
+
+    /// Start answering requests on an ephemeral port of 127.0.0.1.
+    ///
+    /// The server is stopped when the handle is dropped.
+    pub fn spawn(self) -> io::Result<MockHandle> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        socket.set_read_timeout(Some(Duration::from_millis(20)))?;
+        let address = socket.local_addr()?;
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let is_running = is_running.clone();
+            thread::spawn(move || {
+                let mut buffer = vec![0; 64 * 1024]; // 64 kb
+
+                while is_running.load(Ordering::Relaxed) {
+                    let Ok((len, from)) = socket.recv_from(&mut buffer) else {
+                        continue;
+                    };
+                    if let Some(response) = self.respond(&buffer[..len]) {
+                        let _ = socket.send_to(&response, from);
+                    }
+                }
+            })
+        };
+
+        Ok(MockHandle {
+            address,
+            is_running,
+            thread: Some(thread),
+        })
+    }
+
+    fn status(&self, flags: StatusFlags) -> Vec<u8> {
+        let mut body = vec![b'n'];
+
+        if flags.has_serverinfo() {
+            body.extend(unicode::to_bytestr(&serverinfo(&self.settings)));
+            body.push(b'\n');
+        }
+
+        let show_players = flags.is_oldstyle() || flags.contains(StatusFlags::PLAYERS);
+        let show_spectators = flags.contains(StatusFlags::SPECTATORS);
+
+        for client in &self.clients {
+            let is_shown = match client.is_spectator {
+                true => show_spectators,
+                false => show_players,
+            };
+            if is_shown {
+                body.extend(status_row(client, flags));
+                body.push(b'\n');
+            }
+        }
+
+        if let Some(stream) = &self.qtv_stream
+            && flags.contains(StatusFlags::SHOWQTV)
+        {
+            let row = format!(
+                "qtv {} \"{}\" \"{}\" {}\n",
+                stream.id,
+                stream.name,
+                stream.url(),
+                stream.client_count
+            );
+            body.extend(unicode::to_bytestr(&row));
+        }
+
+        body
+    }
+
+    fn qtvusers(&self) -> Option<Vec<u8>> {
+        let stream = self.qtv_stream.as_ref()?;
+        let mut row = format!("nqtvusers {}", stream.id);
+
+        for name in &stream.client_names {
+            row.push_str(&format!(" \"{}\"", name));
+        }
+        row.push('\n');
+
+        Some(unicode::to_bytestr(&row))
+    }
+}
+
+/// A running [`MockServer`].
+#[derive(Debug)]
+pub struct MockHandle {
+    address: SocketAddr,
+    is_running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockHandle {
+    /// Address of the server, e.g. "127.0.0.1:41234".
+    pub fn address(&self) -> String {
+        self.address.to_string()
+    }
+
+    pub fn socket_address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for MockHandle {
+    fn drop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Client row as written by MVDSV.
+///
+/// Spectators are shown with negative ping and name prefixed with `\s\`,
+/// or with frags `S` if requested as players (`SPECTATORS_AS_PLAYERS`).
+fn status_row(client: &QuakeClient, flags: StatusFlags) -> Vec<u8> {
+    let (frags, ping, name) = match client.is_spectator {
+        true if flags.contains(StatusFlags::SPECTATORS_AS_PLAYERS) => {
+            ("S".to_string(), client.ping as i64, client.name.clone())
+        }
+        true => (
+            "0".to_string(),
+            -(client.ping.max(1) as i64),
+            format!("\\s\\{}", client.name),
+        ),
+        false => (
+            client.frags.to_string(),
+            client.ping as i64,
+            client.name.clone(),
+        ),
+    };
+
+    let mut row = format!(
+        "{} {} {} {} \"{}\" \"{}\" {} {}",
+        client.id,
+        frags,
+        client.time,
+        ping,
+        name,
+        client.skin,
+        client.top_color,
+        client.bottom_color
+    );
+
+    if flags.contains(StatusFlags::SHOWTEAMS) {
+        row.push_str(&format!(" \"{}\"", client.team));
+    }
+    if flags.contains(StatusFlags::SHOWFLAGS) {
+        row.push_str(&format!(" \"{}\"", client.auth_cc));
+    }
+
+    unicode::to_bytestr(&row)
+}
+
+/// Serverinfo string of settings, keys as read by [`Settings`].
+fn serverinfo(settings: &Settings) -> String {
+    let s = settings;
+    let values: [(&str, Option<String>); 30] = [
+        ("*admin", s.admin.clone()),
+        ("city", s.city.clone()),
+        ("coords", s.coords.clone()),
+        ("countrycode", s.countrycode.clone()),
+        ("deathmatch", s.deathmatch.map(|v| v.to_string())),
+        ("epoch", s.epoch.map(|v| v.to_string())),
+        ("fpd", s.fpd.map(|v| v.to_string())),
+        ("fraglimit", s.fraglimit.map(|v| v.to_string())),
+        ("*gamedir", s.gamedir.clone()),
+        ("hostname", s.hostname.clone()),
+        ("hostport", s.hostport.clone()),
+        ("ktxmode", s.ktxmode.clone()),
+        ("ktxver", s.ktxver.clone()),
+        ("map", s.map.clone()),
+        ("matchtag", s.matchtag.clone()),
+        ("maxclients", s.maxclients.map(|v| v.to_string())),
+        ("maxfps", s.maxfps.map(|v| v.to_string())),
+        ("maxspectators", s.maxspectators.map(|v| v.to_string())),
+        ("mode", s.mode.clone()),
+        ("needpass", s.needpass.map(|v| v.to_string())),
+        ("pm_ktjump", s.pm_ktjump.map(|v| v.to_string())),
+        ("*progs", s.progs.clone()),
+        ("*qvm", s.qvm.clone()),
+        ("serverdemo", s.serverdemo.clone()),
+        ("status", s.status.clone()),
+        ("sv_antilag", s.sv_antilag.map(|v| v.to_string())),
+        ("teamplay", s.teamplay.map(|v| v.to_string())),
+        ("timelimit", s.timelimit.map(|v| v.to_string())),
+        ("*version", s.version.clone()),
+        ("*z_ext", s.z_ext.map(|v| v.to_string())),
+    ];
+
+    values
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|v| format!("\\{}\\{}", key, v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hostport::Hostport;
+    use crate::svc_status::StatusResponse;
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn client(id: u32, name: &str, is_spectator: bool) -> QuakeClient {
+        QuakeClient {
+            id,
+            name: name.to_string(),
+            team: match is_spectator {
+                true => "".to_string(),
+                false => "red".to_string(),
+            },
+            frags: match is_spectator {
+                true => 0,
+                false => 12,
+            },
+            ping: 25,
+            time: 15,
+            top_color: 4,
+            bottom_color: 4,
+            skin: "base".to_string(),
+            auth_cc: "".to_string(),
+            is_spectator,
+            is_bot: false,
+        }
+    }
+
+    #[test]
+    fn test_respond() -> Result<()> {
+        let server = MockServer::mvdsv("foo")
+            .with_clients(vec![client(1, "XantoM", false), client(2, "bps", true)])
+            .with_qtv_stream(QtvStream {
+                id: 1,
+                name: "foo Qtv (1)".to_string(),
+                number: 1,
+                address: Hostport::new("127.0.0.1".to_string(), 28000),
+                client_count: 1,
+                client_names: vec!["bar".to_string()],
+            });
+
+        assert_eq!(
+            server.respond(b"\xff\xff\xff\xffk"),
+            Some(b"\xff\xff\xff\xffl".to_vec())
+        );
+        assert_eq!(server.respond(b"\xff\xff\xff\xfffoo"), None);
+        assert_eq!(server.respond(b"status 119"), None);
+        assert_eq!(
+            server.respond(b"\xff\xff\xff\xffqtvusers"),
+            Some(b"\xff\xff\xff\xffnqtvusers 1 \"bar\"\n".to_vec())
+        );
+        assert_eq!(
+            server.respond(b"\xff\xff\xff\xffstatus 3"),
+            Some(b"\xff\xff\xff\xffn\\hostname\\foo\\*version\\MVDSV 0.36\n1 12 15 25 \"XantoM\" \"base\" 4 4\n".to_vec())
+        );
+
+        // responses parse back to the same state
+        for bits in [119, 127] {
+            let flags = StatusFlags::from_bits(bits);
+            let bytes = server.respond(&flags.message()).unwrap_or_default();
+            let parsed = StatusResponse::parse_lenient(&bytes, flags)?;
+            assert!(parsed.warnings.is_empty());
+            assert_eq!(parsed.value.settings, server.settings);
+            assert_eq!(parsed.value.clients, server.clients);
+        }
+
+        // old-style rows have no team column, spectators are not listed
+        let bytes = server
+            .respond(b"\xff\xff\xff\xffstatus")
+            .unwrap_or_default();
+        let parsed = StatusResponse::parse_lenient(&bytes, StatusFlags::OLDSTYLE)?;
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(parsed.value.clients.len(), 1);
+        assert_eq!(parsed.value.clients[0].name, "XantoM");
+        assert_eq!(parsed.value.clients[0].team, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawn() -> Result<()> {
+        let handle = MockServer::qwfwd("foo").spawn()?;
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+        socket.send_to(b"\xff\xff\xff\xffk", handle.socket_address())?;
+
+        let mut buf = [0; 64];
+        let (len, _) = socket.recv_from(&mut buf)?;
+        assert_eq!(&buf[..len], b"\xff\xff\xff\xffl");
+        Ok(())
+    }
+}
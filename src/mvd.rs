@@ -0,0 +1,830 @@
+//! MVD (multi view demo) parsing, as recorded by MVDSV and relayed by QTV.
+//!
+//! Only the messages describing the game state (server data, serverinfo,
+//! userinfo, frags, pings etc.) are parsed, entity and player updates are skipped.
+use crate::client::QuakeClient;
+use crate::error::{Error, Result};
+use crate::gameserver::GameServer;
+use crate::protocol_extensions::*;
+use crate::reader::ByteReader;
+use quake_serverinfo::Settings;
+use quake_text::bytestr;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+pub const MAX_CLIENTS: usize = 32;
+pub const PROTOCOL_VERSION: u32 = 28;
+
+// demo message types
+const DEM_CMD: u8 = 0;
+const DEM_READ: u8 = 1;
+const DEM_SET: u8 = 2;
+const DEM_MULTIPLE: u8 = 3;
+const DEM_SINGLE: u8 = 4;
+const DEM_STATS: u8 = 5;
+const DEM_ALL: u8 = 6;
+
+// server to client messages
+const SVC_NOP: u8 = 1;
+const SVC_DISCONNECT: u8 = 2;
+const SVC_UPDATESTAT: u8 = 3;
+const SVC_SOUND: u8 = 6;
+const SVC_PRINT: u8 = 8;
+const SVC_STUFFTEXT: u8 = 9;
+const SVC_SETANGLE: u8 = 10;
+const SVC_SERVERDATA: u8 = 11;
+const SVC_LIGHTSTYLE: u8 = 12;
+const SVC_UPDATEFRAGS: u8 = 14;
+const SVC_STOPSOUND: u8 = 16;
+const SVC_DAMAGE: u8 = 19;
+const SVC_SPAWNSTATIC: u8 = 20;
+const SVC_SPAWNBASELINE: u8 = 22;
+const SVC_TEMP_ENTITY: u8 = 23;
+const SVC_SETPAUSE: u8 = 24;
+const SVC_CENTERPRINT: u8 = 26;
+const SVC_KILLEDMONSTER: u8 = 27;
+const SVC_FOUNDSECRET: u8 = 28;
+const SVC_SPAWNSTATICSOUND: u8 = 29;
+const SVC_INTERMISSION: u8 = 30;
+const SVC_FINALE: u8 = 31;
+const SVC_CDTRACK: u8 = 32;
+const SVC_SELLSCREEN: u8 = 33;
+const SVC_SMALLKICK: u8 = 34;
+const SVC_BIGKICK: u8 = 35;
+const SVC_UPDATEPING: u8 = 36;
+const SVC_UPDATEENTERTIME: u8 = 37;
+const SVC_UPDATESTATLONG: u8 = 38;
+const SVC_MUZZLEFLASH: u8 = 39;
+const SVC_UPDATEUSERINFO: u8 = 40;
+const SVC_PLAYERINFO: u8 = 42;
+const SVC_NAILS: u8 = 43;
+const SVC_CHOKECOUNT: u8 = 44;
+const SVC_MODELLIST: u8 = 45;
+const SVC_SOUNDLIST: u8 = 46;
+const SVC_PACKETENTITIES: u8 = 47;
+const SVC_DELTAPACKETENTITIES: u8 = 48;
+const SVC_MAXSPEED: u8 = 49;
+const SVC_ENTGRAVITY: u8 = 50;
+const SVC_SETINFO: u8 = 51;
+const SVC_SERVERINFO: u8 = 52;
+const SVC_UPDATEPL: u8 = 53;
+const SVC_NAILS2: u8 = 54;
+
+// svc_playerinfo flags (MVD)
+const DF_ORIGIN: u16 = 1;
+const DF_ANGLES: u16 = 1 << 3;
+const DF_EFFECTS: u16 = 1 << 6;
+const DF_SKINNUM: u16 = 1 << 7;
+const DF_WEAPONFRAME: u16 = 1 << 10;
+const DF_MODEL: u16 = 1 << 11;
+
+// entity update bits
+const U_ANGLE1: u16 = 1;
+const U_ANGLE3: u16 = 1 << 1;
+const U_MODEL: u16 = 1 << 2;
+const U_COLORMAP: u16 = 1 << 3;
+const U_SKIN: u16 = 1 << 4;
+const U_EFFECTS: u16 = 1 << 5;
+const U_FTE_EVENMORE: u16 = 1 << 7;
+const U_ORIGIN1: u16 = 1 << 9;
+const U_ORIGIN2: u16 = 1 << 10;
+const U_ORIGIN3: u16 = 1 << 11;
+const U_ANGLE2: u16 = 1 << 12;
+const U_FRAME: u16 = 1 << 13;
+const U_REMOVE: u16 = 1 << 14;
+const U_MOREBITS: u16 = 1 << 15;
+
+// temp entities
+const TE_GUNSHOT: u8 = 2;
+const TE_LIGHTNING1: u8 = 5;
+const TE_LIGHTNING2: u8 = 6;
+const TE_LIGHTNING3: u8 = 9;
+const TE_BLOOD: u8 = 12;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerData {
+    pub protocol: u32,
+    pub extensions: ProtocolExtensions,
+    pub servercount: i32,
+    pub gamedir: String,
+    pub demotime: f32,
+    /// Full name of the level, e.g. "The Abandoned Base".
+    pub level_name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MvdPlayer {
+    pub userid: u32,
+    pub userinfo: HashMap<String, String>,
+    pub frags: i32,
+    pub ping: u32,
+    pub packet_loss: u8,
+    /// Demo time (see [`MvdState::time`]) the player entered the game, in
+    /// seconds. Negative if the player entered before the demo started.
+    pub entertime: f32,
+}
+
+impl MvdPlayer {
+    /// Time connected at the given demo time, zero if out of range.
+    pub fn connected(&self, time: Duration) -> Duration {
+        let seconds = (time.as_secs_f32() - self.entertime).max(0.0);
+        Duration::try_from_secs_f32(seconds).unwrap_or_default()
+    }
+
+    /// Client at the given demo time.
+    pub fn to_client(&self, time: Duration) -> QuakeClient {
+        let info = |key: &str| self.userinfo.get(key).cloned().unwrap_or_default();
+        let info_u8 = |key: &str| info(key).parse::<u8>().unwrap_or_default();
+
+        QuakeClient {
+            id: self.userid,
+            name: info("name"),
+            team: info("team"),
+            frags: self.frags,
+            ping: self.ping,
+            time: (self.connected(time).as_secs() / 60) as u32,
+            top_color: info_u8("topcolor"),
+            bottom_color: info_u8("bottomcolor"),
+            skin: info("skin"),
+            auth_cc: "".to_string(),
+            is_spectator: !matches!(info("*spectator").as_str(), "" | "0"),
+            is_bot: info("*bot") == "1",
+        }
+    }
+}
+
+/// Game state built from MVD messages.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MvdState {
+    pub serverdata: Option<ServerData>,
+    pub serverinfo: HashMap<String, String>,
+    pub players: HashMap<u8, MvdPlayer>,
+    pub models: Vec<String>,
+    /// Sum of frame durations.
+    pub time: Duration,
+    /// Set when the first entity/player update is read, i.e. the initial state is complete.
+    pub in_game: bool,
+    pub is_disconnected: bool,
+}
+
+impl MvdState {
+    /// Parse complete frames from the given bytes, returns the number of bytes consumed.
+    ///
+    /// Trailing bytes of an incomplete frame are not consumed.
+    pub fn parse_frames(&mut self, bytes: &[u8]) -> Result<usize> {
+        let mut consumed = 0;
+
+        while let Some(len) = frame_len(&bytes[consumed..])? {
+            self.parse_frame(&bytes[consumed..consumed + len])?;
+            consumed += len;
+        }
+
+        Ok(consumed)
+    }
+
+    fn parse_frame(&mut self, frame: &[u8]) -> Result<()> {
+        let mut reader = ByteReader::new(frame);
+        self.time += Duration::from_millis(reader.read_u8()? as u64);
+        let kind = reader.read_u8()? & 7;
+
+        let data = match kind {
+            DEM_SET => return Ok(()),
+            DEM_MULTIPLE => {
+                // hidden messages (no receivers) hold MVDSV specific data, not svc messages
+                let mask = reader.read_i32()?;
+                let len = reader.read_i32()? as usize;
+                match mask {
+                    0 => return Ok(()),
+                    _ => reader.read_bytes(len)?,
+                }
+            }
+            _ => {
+                let len = reader.read_i32()? as usize;
+                reader.read_bytes(len)?
+            }
+        };
+
+        // messages that can't be parsed are skipped until the end of the frame
+        let mut reader = ByteReader::new(data);
+        while !reader.is_empty() {
+            if self.parse_message(&mut reader).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_message(&mut self, reader: &mut ByteReader) -> Result<()> {
+        let coord_size = match self.serverdata.as_ref() {
+            Some(sd) if sd.extensions.has_mvd(MVD_PEXT1_FLOATCOORDS) => 4,
+            _ => 2,
+        };
+        let angle_size = match coord_size {
+            4 => 2,
+            _ => 1,
+        };
+
+        let svc = reader.read_u8()?;
+
+        match svc {
+            SVC_NOP | SVC_SMALLKICK | SVC_BIGKICK | SVC_KILLEDMONSTER | SVC_FOUNDSECRET
+            | SVC_SELLSCREEN => {}
+            SVC_DISCONNECT => self.is_disconnected = true,
+            SVC_SETPAUSE | SVC_CDTRACK | SVC_CHOKECOUNT => {
+                reader.read_u8()?;
+            }
+            SVC_UPDATESTAT => {
+                reader.read_bytes(2)?;
+            }
+            SVC_UPDATEPL => {
+                let slot = reader.read_u8()?;
+                self.player(slot)?.packet_loss = reader.read_u8()?;
+            }
+            SVC_PRINT => {
+                reader.read_u8()?;
+                reader.read_string()?;
+            }
+            SVC_CENTERPRINT | SVC_FINALE => {
+                reader.read_string()?;
+            }
+            SVC_STUFFTEXT => {
+                let text = bytestr::to_unicode(reader.read_string()?);
+                if let Some(info) = text.trim().strip_prefix("fullserverinfo ") {
+                    self.serverinfo = quake_infostring::to_hashmap(info.trim_matches('"'));
+                }
+            }
+            SVC_SERVERDATA => self.serverdata = Some(read_serverdata(reader)?),
+            SVC_LIGHTSTYLE => {
+                reader.read_u8()?;
+                reader.read_string()?;
+            }
+            SVC_UPDATEFRAGS => {
+                let slot = reader.read_u8()?;
+                self.player(slot)?.frags = reader.read_i16()? as i32;
+            }
+            SVC_UPDATEPING => {
+                let slot = reader.read_u8()?;
+                self.player(slot)?.ping = reader.read_i16()?.unsigned_abs() as u32;
+            }
+            SVC_UPDATEENTERTIME => {
+                // seconds connected at the time of the message
+                let slot = reader.read_u8()?;
+                let connected = reader.read_f32()?;
+                if !connected.is_finite() {
+                    return Err(Error::InvalidBody);
+                }
+                let time = self.time.as_secs_f32();
+                self.player(slot)?.entertime = time - connected;
+            }
+            SVC_UPDATEUSERINFO => {
+                let slot = reader.read_u8()?;
+                let userid = reader.read_i32()? as u32;
+                let userinfo = bytestr::to_unicode(reader.read_string()?);
+
+                if userinfo.is_empty() {
+                    self.players.remove(&slot);
+                } else {
+                    let player = self.player(slot)?;
+                    player.userid = userid;
+                    player.userinfo = quake_infostring::to_hashmap(&userinfo);
+                }
+            }
+            SVC_SETINFO => {
+                let slot = reader.read_u8()?;
+                let key = bytestr::to_unicode(reader.read_string()?);
+                let value = bytestr::to_unicode(reader.read_string()?);
+                self.player(slot)?.userinfo.insert(key, value);
+            }
+            SVC_SERVERINFO => {
+                let key = bytestr::to_unicode(reader.read_string()?);
+                let value = bytestr::to_unicode(reader.read_string()?);
+                self.serverinfo.insert(key, value);
+            }
+            SVC_STOPSOUND | SVC_MUZZLEFLASH => {
+                reader.read_bytes(2)?;
+            }
+            SVC_SOUND => {
+                let channel = reader.read_i16()? as u16;
+                let extra = (channel >> 15) + ((channel >> 14) & 1); // volume, attenuation
+                reader.read_bytes(extra as usize + 1 + 3 * coord_size)?;
+            }
+            SVC_SETANGLE => {
+                reader.read_bytes(1 + 3 * angle_size)?;
+            }
+            SVC_DAMAGE => {
+                reader.read_bytes(2 + 3 * coord_size)?;
+            }
+            SVC_TEMP_ENTITY => {
+                let len = match reader.read_u8()? {
+                    TE_GUNSHOT | TE_BLOOD => 1 + 3 * coord_size,
+                    TE_LIGHTNING1 | TE_LIGHTNING2 | TE_LIGHTNING3 => 2 + 6 * coord_size,
+                    _ => 3 * coord_size,
+                };
+                reader.read_bytes(len)?;
+            }
+            SVC_NAILS | SVC_NAILS2 => {
+                let count = reader.read_u8()? as usize;
+                let size = match svc {
+                    SVC_NAILS => 6,
+                    _ => 7,
+                };
+                reader.read_bytes(count * size)?;
+            }
+            SVC_UPDATESTATLONG => {
+                reader.read_bytes(5)?;
+            }
+            SVC_MAXSPEED | SVC_ENTGRAVITY => {
+                reader.read_f32()?;
+            }
+            SVC_SPAWNSTATIC => {
+                reader.read_bytes(4 + 3 * (coord_size + angle_size))?;
+            }
+            SVC_SPAWNBASELINE => {
+                reader.read_bytes(2 + 4 + 3 * (coord_size + angle_size))?;
+            }
+            SVC_SPAWNSTATICSOUND => {
+                reader.read_bytes(3 * coord_size + 3)?;
+            }
+            SVC_INTERMISSION => {
+                reader.read_bytes(3 * coord_size + 3 * angle_size)?;
+            }
+            SVC_MODELLIST | SVC_SOUNDLIST => {
+                // start index, names, next index
+                reader.read_u8()?;
+                loop {
+                    let name = reader.read_string()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    if svc == SVC_MODELLIST {
+                        self.models.push(bytestr::to_unicode(name));
+                    }
+                }
+                reader.read_u8()?;
+            }
+            SVC_PLAYERINFO => {
+                self.in_game = true;
+                reader.read_u8()?;
+                let flags = reader.read_i16()? as u16;
+                reader.read_u8()?; // frame
+
+                let mut len = 0;
+                for i in 0..3 {
+                    if flags & (DF_ORIGIN << i) != 0 {
+                        len += coord_size;
+                    }
+                    if flags & (DF_ANGLES << i) != 0 {
+                        len += 2;
+                    }
+                }
+                for bit in [DF_MODEL, DF_SKINNUM, DF_EFFECTS, DF_WEAPONFRAME] {
+                    len += (flags & bit != 0) as usize;
+                }
+                reader.read_bytes(len)?;
+            }
+            SVC_PACKETENTITIES | SVC_DELTAPACKETENTITIES => {
+                self.in_game = true;
+                if svc == SVC_DELTAPACKETENTITIES {
+                    reader.read_u8()?; // from
+                }
+                read_packetentities(reader, coord_size, angle_size)?;
+            }
+            _ => return Err(Error::parse("message", &svc.to_string())),
+        }
+
+        Ok(())
+    }
+
+    fn player(&mut self, slot: u8) -> Result<&mut MvdPlayer> {
+        if slot as usize >= MAX_CLIENTS {
+            return Err(Error::parse("player slot", &slot.to_string()));
+        }
+        Ok(self.players.entry(slot).or_default())
+    }
+
+    /// Serverinfo settings, the map is taken from the model list if missing.
+    pub fn settings(&self) -> Settings {
+        let mut info = self.serverinfo.clone();
+
+        if let Some(map) = self
+            .models
+            .first()
+            .and_then(|m| m.strip_prefix("maps/"))
+            .and_then(|m| m.strip_suffix(".bsp"))
+        {
+            info.entry("map".to_string())
+                .or_insert_with(|| map.to_string());
+        }
+
+        Settings::from(&info)
+    }
+
+    pub fn clients(&self) -> Vec<QuakeClient> {
+        self.players
+            .values()
+            .filter(|p| !p.userinfo.is_empty())
+            .map(|p| p.to_client(self.time))
+            .collect()
+    }
+
+    pub fn to_gameserver(&self) -> GameServer {
+        GameServer::new(&self.settings(), &self.clients(), None)
+    }
+}
+
+/// Game state at a point in time of a demo.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MvdSnapshot {
+    pub time: Duration,
+    pub server: GameServer,
+}
+
+/// Final scoreboard of a demo file.
+pub fn from_file(path: impl AsRef<Path>) -> Result<GameServer> {
+    let bytes = fs::read(path)?;
+    Ok(from_bytes(&bytes)?.server)
+}
+
+/// Final scoreboard of a demo.
+pub fn from_bytes(bytes: &[u8]) -> Result<MvdSnapshot> {
+    snapshots(bytes, None)?.pop().ok_or(Error::InvalidHeader)
+}
+
+/// Snapshots of a demo at every interval of demo time (if any), the last one
+/// being the final scoreboard.
+///
+/// Truncated demos are read up to the last complete frame.
+pub fn snapshots(bytes: &[u8], interval: Option<Duration>) -> Result<Vec<MvdSnapshot>> {
+    if interval.is_some_and(|i| i.is_zero()) {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid interval",
+        )));
+    }
+
+    let mut state = MvdState::default();
+    let mut snapshots: Vec<MvdSnapshot> = vec![];
+    let mut next = interval.unwrap_or_default();
+    let mut pos = 0;
+
+    while let Some(len) = frame_len(&bytes[pos..])? {
+        state.parse_frame(&bytes[pos..pos + len])?;
+        pos += len;
+
+        if state.is_disconnected {
+            break;
+        }
+
+        if let Some(interval) = interval
+            && state.time >= next
+            && state.serverdata.is_some()
+        {
+            snapshots.push(MvdSnapshot {
+                time: state.time,
+                server: state.to_gameserver(),
+            });
+
+            // next multiple of the interval
+            let count = state.time.as_nanos() / interval.as_nanos() + 1;
+            next = Duration::from_nanos((count * interval.as_nanos()) as u64);
+        }
+    }
+
+    if state.serverdata.is_none() {
+        return Err(Error::InvalidHeader);
+    }
+
+    if snapshots.last().is_none_or(|s| s.time != state.time) {
+        snapshots.push(MvdSnapshot {
+            time: state.time,
+            server: state.to_gameserver(),
+        });
+    }
+
+    Ok(snapshots)
+}
+
+/// Length of the first frame in the given bytes, `None` if incomplete.
+fn frame_len(bytes: &[u8]) -> Result<Option<usize>> {
+    // duration, command
+    let Some(&command) = bytes.get(1) else {
+        return Ok(None);
+    };
+
+    let header_len = match command & 7 {
+        DEM_CMD => return Err(Error::parse("demo message", &command.to_string())),
+        DEM_SET => return Ok((bytes.len() >= 10).then_some(10)),
+        DEM_MULTIPLE => 2 + 4 + 4,
+        DEM_READ | DEM_SINGLE | DEM_STATS | DEM_ALL => 2 + 4,
+        _ => return Err(Error::parse("demo message", &command.to_string())),
+    };
+
+    if bytes.len() < header_len {
+        return Ok(None);
+    }
+
+    let len_bytes = &bytes[header_len - 4..header_len];
+    let len = i32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]);
+    let len = usize::try_from(len).map_err(|_| Error::parse("demo message", &len.to_string()))?;
+
+    match bytes.len() >= header_len + len {
+        true => Ok(Some(header_len + len)),
+        false => Ok(None),
+    }
+}
+
+fn read_packetentities(
+    reader: &mut ByteReader,
+    coord_size: usize,
+    angle_size: usize,
+) -> Result<()> {
+    loop {
+        // low 9 bits of the first word is the entity number
+        let word = reader.read_i16()? as u16;
+
+        if word == 0 {
+            return Ok(());
+        }
+
+        let mut bits = word & !511;
+        if bits & U_MOREBITS != 0 {
+            bits |= reader.read_u8()? as u16;
+        }
+        if bits & U_FTE_EVENMORE != 0 {
+            return Err(Error::parse("entity update", &bits.to_string()));
+        }
+        if bits & U_REMOVE != 0 {
+            continue;
+        }
+
+        let mut len = 0;
+        for bit in [U_MODEL, U_FRAME, U_COLORMAP, U_SKIN, U_EFFECTS] {
+            len += (bits & bit != 0) as usize;
+        }
+        for bit in [U_ORIGIN1, U_ORIGIN2, U_ORIGIN3] {
+            len += (bits & bit != 0) as usize * coord_size;
+        }
+        for bit in [U_ANGLE1, U_ANGLE2, U_ANGLE3] {
+            len += (bits & bit != 0) as usize * angle_size;
+        }
+        reader.read_bytes(len)?;
+    }
+}
+
+fn read_serverdata(reader: &mut ByteReader) -> Result<ServerData> {
+    let mut extensions = ProtocolExtensions::default();
+
+    // extension pairs precede the protocol version
+    let protocol = loop {
+        let protocol = reader.read_i32()? as u32;
+        match protocol {
+            PROTOCOL_VERSION => break protocol,
+            PROTOCOL_VERSION_FTE | PROTOCOL_VERSION_FTE2 | PROTOCOL_VERSION_MVD1 => {
+                extensions.set(protocol, reader.read_i32()? as u32);
+            }
+            _ => return Err(Error::parse("protocol version", &protocol.to_string())),
+        }
+    };
+
+    let servercount = reader.read_i32()?;
+    let gamedir = bytestr::to_unicode(reader.read_string()?);
+    let demotime = reader.read_f32()?;
+    let level_name = bytestr::to_unicode(reader.read_string()?);
+    reader.read_bytes(10 * 4)?; // movevars
+
+    Ok(ServerData {
+        protocol,
+        extensions,
+        servercount,
+        gamedir,
+        demotime,
+        level_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameserver::{Player, Spectator};
+    use crate::team::Team;
+    use pretty_assertions::assert_eq;
+
+    const SNAPSHOT: &[u8] = include_bytes!("../tests/fixtures/snapshot.mvd");
+    const DEMO: &[u8] = include_bytes!("../tests/fixtures/demo.mvd");
+
+    #[test]
+    fn test_frame_len() -> Result<()> {
+        assert_eq!(frame_len(&[])?, None);
+        assert_eq!(frame_len(&[0, DEM_SET, 1, 0, 0])?, None);
+        assert_eq!(frame_len(&[0, DEM_SET, 1, 0, 0, 0, 1, 0, 0, 0])?, Some(10));
+        assert_eq!(frame_len(&[0, DEM_ALL, 2, 0, 0, 0, 1])?, None);
+        assert_eq!(frame_len(&[0, DEM_ALL, 2, 0, 0, 0, 1, 1, 1])?, Some(8));
+        assert_eq!(
+            frame_len(&[0, DEM_MULTIPLE, 0, 0, 0, 0, 1, 0, 0, 0, 1])?,
+            Some(11)
+        );
+        assert_eq!(
+            frame_len(&[0, DEM_CMD]).unwrap_err().to_string(),
+            "Invalid demo message"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_frames() -> Result<()> {
+        // incomplete frames are not consumed
+        let mut state = MvdState::default();
+        let len = SNAPSHOT.len() - 3;
+        let consumed = state.parse_frames(&SNAPSHOT[..len])?;
+        assert!(consumed < len);
+        assert!(!state.in_game);
+        assert_eq!(
+            state.parse_frames(&SNAPSHOT[consumed..])?,
+            SNAPSHOT.len() - consumed
+        );
+        assert!(state.in_game);
+        assert_eq!(state.time, Duration::from_millis(26));
+
+        let serverdata = state.serverdata.clone().unwrap_or_default();
+        assert_eq!(serverdata.protocol, PROTOCOL_VERSION);
+        assert_eq!(serverdata.extensions.mvd, Some(0));
+        assert_eq!(serverdata.gamedir, "qw");
+        assert_eq!(serverdata.demotime, 12.5);
+        assert_eq!(serverdata.level_name, "The Abandoned Base");
+        assert_eq!(state.models, vec!["maps/dm3.bsp", "progs/player.mdl"]);
+        assert_eq!(state.players.len(), 3);
+
+        // entered 600 seconds before the update, connected time advances with demo time
+        let player = &state.players[&0];
+        assert!(player.entertime < -599.99 && player.entertime >= -600.0);
+        let connected = player.connected(state.time + Duration::from_secs(60));
+        assert!(
+            connected >= Duration::from_secs(660) && connected < Duration::from_millis(660_030)
+        );
+        assert_eq!(player.to_client(Duration::from_secs(60)).time, 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_entertime_out_of_range() -> Result<()> {
+        let mut state = MvdState::default();
+        let message =
+            |seconds: f32| [&[SVC_UPDATEENTERTIME, 0][..], &seconds.to_le_bytes()].concat();
+
+        for seconds in [f32::INFINITY, f32::NAN] {
+            let bytes = message(seconds);
+            let res = state.parse_message(&mut ByteReader::new(&bytes));
+            assert!(matches!(res, Err(Error::InvalidBody)));
+        }
+        assert!(state.players.is_empty());
+
+        // too long ago to be represented, the connected time falls back to zero
+        state.parse_message(&mut ByteReader::new(&message(1e30)))?;
+        let player = &state.players[&0];
+        assert_eq!(player.connected(Duration::from_secs(60)), Duration::ZERO);
+        assert_eq!(player.to_client(Duration::from_secs(60)).time, 0);
+
+        let player = MvdPlayer {
+            entertime: f32::NEG_INFINITY,
+            ..Default::default()
+        };
+        assert_eq!(player.connected(Duration::from_secs(60)), Duration::ZERO);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_gameserver() -> Result<()> {
+        let mut state = MvdState::default();
+        state.parse_frames(SNAPSHOT)?;
+        let server = state.to_gameserver();
+
+        assert_eq!(
+            server.settings.hostname,
+            Some("QUAKE.SE KTX:28501".to_string())
+        );
+        assert_eq!(server.settings.map, Some("dm3".to_string()));
+        assert_eq!(server.settings.status, Some("3 min left".to_string()));
+        assert_eq!(
+            server.players,
+            vec![
+                Player {
+                    id: 415,
+                    name: "bps".to_string(),
+                    team: "blue".to_string(),
+                    frags: -2,
+                    ping: 12,
+                    top_color: 13,
+                    bottom_color: 13,
+                    ..Default::default()
+                },
+                Player {
+                    id: 412,
+                    name: "XantoM".to_string(),
+                    team: "red".to_string(),
+                    frags: 23,
+                    ping: 25,
+                    time: 10,
+                    top_color: 4,
+                    bottom_color: 4,
+                    skin: "base".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            server.spectators,
+            vec![Spectator {
+                id: 418,
+                name: "razor".to_string(),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(
+            server.teams,
+            vec![
+                Team {
+                    name: "blue".to_string(),
+                    frags: -2,
+                    ping: 12,
+                    top_color: 13,
+                    bottom_color: 13,
+                },
+                Team {
+                    name: "red".to_string(),
+                    frags: 23,
+                    ping: 25,
+                    top_color: 4,
+                    bottom_color: 4,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_bytes() -> Result<()> {
+        assert!(matches!(from_bytes(&[]), Err(Error::InvalidHeader)));
+
+        let snapshot = from_bytes(DEMO)?;
+        assert_eq!(snapshot.time, Duration::from_millis(476));
+        assert_eq!(snapshot.server.settings.map, Some("dm3".to_string()));
+
+        let frags: Vec<(String, i32)> = snapshot
+            .server
+            .players
+            .iter()
+            .map(|p| (p.name.clone(), p.frags))
+            .collect();
+        assert_eq!(
+            frags,
+            vec![("bps".to_string(), 5), ("XantoM".to_string(), 31)]
+        );
+
+        // truncated
+        assert_eq!(
+            from_bytes(&DEMO[..DEMO.len() - 20])?.time,
+            Duration::from_millis(226)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_file() -> Result<()> {
+        let server = from_file("tests/fixtures/demo.mvd")?;
+        assert_eq!(server.players.len(), 2);
+        assert!(from_file("tests/fixtures/missing.mvd").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshots() -> Result<()> {
+        assert_eq!(
+            snapshots(DEMO, Some(Duration::ZERO))
+                .unwrap_err()
+                .to_string(),
+            "Invalid interval"
+        );
+
+        let times = |interval: Option<Duration>| -> Result<Vec<u64>> {
+            Ok(snapshots(DEMO, interval)?
+                .iter()
+                .map(|s| s.time.as_millis() as u64)
+                .collect())
+        };
+        assert_eq!(times(None)?, vec![476]);
+        assert_eq!(times(Some(Duration::from_millis(100)))?, vec![226, 476]);
+        assert_eq!(
+            times(Some(Duration::from_millis(10)))?,
+            vec![13, 26, 226, 476]
+        );
+
+        let snapshots = snapshots(DEMO, Some(Duration::from_millis(100)))?;
+        assert_eq!(snapshots[0].server.players[1].frags, 30);
+        assert_eq!(snapshots[1].server.players[1].frags, 31);
+        Ok(())
+    }
+}
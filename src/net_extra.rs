@@ -1,23 +1,120 @@
-use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
 
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::error::{Error, Result},
+    crate::resolver,
+    crate::rt::{self, UdpSocket},
+    std::net::{Ipv4Addr, Ipv6Addr},
+};
+
+/// Options for reading a response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReadOptions {
+    pub timeout: Duration,
+    pub buffer_size: usize,
+}
+
+/// Resolve the ip of an address, blocking. Prefer `crate::resolver` in async code.
 pub fn address_to_ip(address: &str) -> Option<String> {
-    let host = address.split_once(':').map_or(address, |(h, _)| h);
+    let host = split_host(address);
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Some(ip.to_string());
+    }
+
+    let addrs: Vec<SocketAddr> = address.to_socket_addrs().ok()?.collect();
+    preferred(&addrs).map(|addr| addr.ip().to_string())
+}
+
+/// Host part of an address, e.g. "[::1]:27500" -> "::1", "quake.se:28501" -> "quake.se".
+fn split_host(address: &str) -> &str {
+    if let Some(rest) = address.strip_prefix('[') {
+        return rest.split_once(']').map_or(rest, |(h, _)| h);
+    }
+
+    // bare ipv6 literal
+    if address.matches(':').count() > 1 {
+        return address;
+    }
+
+    address.split_once(':').map_or(address, |(h, _)| h)
+}
+
+/// IPv4 addresses are preferred, IPv6 is used if there are no IPv4 addresses.
+pub(crate) fn preferred(addrs: &[SocketAddr]) -> Option<SocketAddr> {
+    addrs
+        .iter()
+        .find(|a| a.is_ipv4())
+        .or(addrs.first())
+        .copied()
+}
 
-    if host.parse::<Ipv4Addr>().is_ok() {
-        return Some(host.to_string());
+/// Resolve an address using the global (cached) resolver.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(crate) async fn resolve(address: &str) -> Result<SocketAddr> {
+    let addrs = resolve_all(address).await?;
+    preferred(&addrs).ok_or(Error::Resolve(address.to_string()))
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(crate) async fn resolve_all(address: &str) -> Result<Vec<SocketAddr>> {
+    // ip literals need no lookup
+    if let Ok(socket_address) = address.parse::<SocketAddr>() {
+        return Ok(vec![socket_address]);
     }
 
-    address
-        .to_socket_addrs()
-        .ok()?
-        .filter_map(|addr| {
-            if let SocketAddr::V4(v4_addr) = addr {
-                Some(v4_addr.ip().to_string())
-            } else {
-                None
+    resolver::global()
+        .resolve(address)
+        .await
+        .map_err(|_| Error::Resolve(address.to_string()))
+}
+
+/// Bind a UDP socket on an ephemeral port, of the same address family as the target.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(crate) async fn bind_for(target: &SocketAddr) -> Result<UdpSocket> {
+    let ip = match target {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    Ok(UdpSocket::bind(SocketAddr::new(ip, 0)).await?)
+}
+
+/// Send a message to the specified address and read the response (IPv4 or IPv6).
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn send_and_receive(
+    address: &str,
+    message: &[u8],
+    options: ReadOptions,
+) -> Result<Vec<u8>> {
+    let socket_address = resolve(address).await?;
+    send_and_receive_to(socket_address, message, options).await
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub(crate) async fn send_and_receive_to(
+    socket_address: SocketAddr,
+    message: &[u8],
+    options: ReadOptions,
+) -> Result<Vec<u8>> {
+    let socket = bind_for(&socket_address).await?;
+    socket.send_to(message, socket_address).await?;
+
+    // ignore datagrams from other sources
+    let mut buffer = vec![0; options.buffer_size];
+    let bytes_read = rt::timeout(options.timeout, async {
+        loop {
+            let (bytes_read, from) = socket.recv_from(&mut buffer).await?;
+            if from == socket_address {
+                return Ok::<usize, Error>(bytes_read);
             }
-        })
-        .next()
+        }
+    })
+    .await
+    .map_err(|_| Error::Timeout)??;
+
+    Ok(buffer[..bytes_read].to_vec())
 }
 
 #[cfg(test)]
@@ -35,4 +132,107 @@ pub mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_address_to_ip_literals() {
+        assert_eq!(address_to_ip("1.2.3.4:27500"), Some("1.2.3.4".to_string()));
+        assert_eq!(
+            address_to_ip("[2001:db8::1]:27500"),
+            Some("2001:db8::1".to_string())
+        );
+        assert_eq!(
+            address_to_ip("2001:db8::1"),
+            Some("2001:db8::1".to_string())
+        );
+        assert_eq!(address_to_ip("[::1]:27500"), Some("::1".to_string()));
+    }
+
+    #[test]
+    fn test_preferred() {
+        let v4: SocketAddr = "10.0.0.1:27500".parse().unwrap();
+        let v6: SocketAddr = "[::1]:27500".parse().unwrap();
+        assert_eq!(preferred(&[]), None);
+        assert_eq!(preferred(&[v6]), Some(v6));
+        assert_eq!(preferred(&[v6, v4]), Some(v4));
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_send_and_receive() -> Result<()> {
+        let options = || ReadOptions {
+            timeout: Duration::from_millis(200),
+            buffer_size: 64,
+        };
+
+        for bind_address in ["127.0.0.1:0", "[::1]:0"] {
+            // skip if the loopback interface lacks ipv6
+            let Ok(server) = tokio::net::UdpSocket::bind(bind_address).await else {
+                continue;
+            };
+            let address = server.local_addr()?.to_string();
+            tokio::spawn(async move {
+                let mut buf = [0; 64];
+                let (len, from) = server.recv_from(&mut buf).await?;
+                server.send_to(&buf[..len], from).await?;
+                Ok::<(), anyhow::Error>(())
+            });
+
+            assert_eq!(
+                send_and_receive(&address, b"ping", options()).await?,
+                b"ping"
+            );
+            let err = send_and_receive(&address, b"ping", options())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::Timeout));
+            assert_eq!(
+                err.to_string(),
+                "Timeout reached while waiting for response"
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_send_and_receive_other_source() -> Result<()> {
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let other = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let (len, from) = server.recv_from(&mut buf).await?;
+            other.send_to(b"spoofed", from).await?;
+            server.send_to(&buf[..len], from).await?;
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let options = ReadOptions {
+            timeout: Duration::from_millis(200),
+            buffer_size: 64,
+        };
+        assert_eq!(send_and_receive(&address, b"ping", options).await?, b"ping");
+        Ok(())
+    }
+
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn test_send_and_receive_smol() -> Result<()> {
+        let server = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let address = server.local_addr()?.to_string();
+        std::thread::spawn(move || {
+            let mut buf = [0; 64];
+            if let Ok((len, from)) = server.recv_from(&mut buf) {
+                let _ = server.send_to(&buf[..len], from);
+            }
+        });
+
+        let options = ReadOptions {
+            timeout: Duration::from_millis(200),
+            buffer_size: 64,
+        };
+        let res = smol::block_on(send_and_receive(&address, b"ping", options))?;
+        assert_eq!(res, b"ping");
+        Ok(())
+    }
 }
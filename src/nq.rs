@@ -0,0 +1,386 @@
+//! NetQuake (NQ) server queries using control packets.
+//!
+//! see: https://github.com/id-Software/Quake/blob/master/WinQuake/net_dgrm.c
+use crate::client::QuakeClient;
+use crate::error::{Error, Result};
+use crate::reader::ByteReader;
+use quake_serverinfo::Settings;
+use quake_text::bytestr;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {crate::net_extra, std::collections::HashMap, std::time::Duration};
+
+const NETFLAG_CTL: u32 = 0x8000_0000;
+const NETFLAG_LENGTH_MASK: u32 = 0x0000_ffff;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+const NET_PROTOCOL_VERSION: u8 = 3;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+const CCREQ_SERVER_INFO: u8 = 0x02;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+const CCREQ_PLAYER_INFO: u8 = 0x03;
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+const CCREQ_RULE_INFO: u8 = 0x04;
+const CCREP_SERVER_INFO: u8 = 0x83;
+const CCREP_PLAYER_INFO: u8 = 0x84;
+const CCREP_RULE_INFO: u8 = 0x85;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn server_info(address: &str, timeout: Duration) -> Result<ServerInfoResponse> {
+    let mut payload = b"QUAKE\0".to_vec();
+    payload.push(NET_PROTOCOL_VERSION);
+    let bytes = send_and_receive(address, CCREQ_SERVER_INFO, &payload, timeout).await?;
+    ServerInfoResponse::try_from(bytes.as_slice())
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn player_info(
+    address: &str,
+    number: u8,
+    timeout: Duration,
+) -> Result<PlayerInfoResponse> {
+    let bytes = send_and_receive(address, CCREQ_PLAYER_INFO, &[number], timeout).await?;
+    PlayerInfoResponse::try_from(bytes.as_slice())
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn rule_info(
+    address: &str,
+    previous_rule: &str,
+    timeout: Duration,
+) -> Result<RuleInfoResponse> {
+    let mut payload = previous_rule.as_bytes().to_vec();
+    payload.push(0);
+    let bytes = send_and_receive(address, CCREQ_RULE_INFO, &payload, timeout).await?;
+    RuleInfoResponse::try_from(bytes.as_slice())
+}
+
+/// Get all rules (server cvars), one request per rule.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn rules(address: &str, timeout: Duration) -> Result<Vec<(String, String)>> {
+    const MAX_RULES: usize = 256;
+    let mut rules: Vec<(String, String)> = vec![];
+
+    while rules.len() < MAX_RULES {
+        let previous = rules.last().map(|(k, _)| k.as_str()).unwrap_or("");
+        match rule_info(address, previous, timeout).await?.rule {
+            Some(rule) => rules.push(rule),
+            None => break,
+        }
+    }
+
+    Ok(rules)
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct NqResponse {
+    pub settings: Settings,
+    pub clients: Vec<QuakeClient>,
+    pub rules: Vec<(String, String)>,
+}
+
+/// Get server info, players and rules.
+///
+/// Rules are optional, servers that do not answer rule requests get an empty
+/// list of rules.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn query(address: &str, timeout: Duration) -> Result<NqResponse> {
+    let info = server_info(address, timeout).await?;
+
+    let mut clients: Vec<QuakeClient> = vec![];
+    for number in 0..info.player_count {
+        if let Ok(player) = player_info(address, number, timeout).await {
+            clients.push(QuakeClient::from(&player));
+        }
+    }
+
+    let rules = rules(address, timeout).await.unwrap_or_default();
+
+    let mut values: HashMap<String, String> = rules.iter().cloned().collect();
+    values.insert("hostname".to_string(), info.hostname.clone());
+    values.insert("map".to_string(), info.map.clone());
+    values.insert("maxclients".to_string(), info.max_players.to_string());
+
+    Ok(NqResponse {
+        settings: Settings::from(&values),
+        clients,
+        rules,
+    })
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+async fn send_and_receive(
+    address: &str,
+    command: u8,
+    payload: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>> {
+    let message = control_packet(command, payload);
+    let options = net_extra::ReadOptions {
+        timeout,
+        buffer_size: 8 * 1024, // 8 kb
+    };
+    net_extra::send_and_receive(address, &message, options).await
+}
+
+#[cfg(any(test, feature = "tokio", feature = "async-io"))]
+fn control_packet(command: u8, payload: &[u8]) -> Vec<u8> {
+    let length = (4 + 1 + payload.len()) as u32;
+    let mut packet = (NETFLAG_CTL | (length & NETFLAG_LENGTH_MASK))
+        .to_be_bytes()
+        .to_vec();
+    packet.push(command);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Validate control header and reply command, returns the reply body.
+fn control_body(bytes: &[u8], command: u8) -> Result<&[u8]> {
+    if bytes.len() < 5 {
+        return Err(Error::InvalidHeader);
+    }
+
+    let header = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    if header & NETFLAG_CTL == 0 || bytes[4] != command {
+        return Err(Error::InvalidHeader);
+    }
+
+    let length = ((header & NETFLAG_LENGTH_MASK) as usize).min(bytes.len());
+    Ok(&bytes[5..length.max(5)])
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ServerInfoResponse {
+    pub address: String,
+    pub hostname: String,
+    pub map: String,
+    pub player_count: u8,
+    pub max_players: u8,
+    pub protocol_version: u8,
+}
+
+impl TryFrom<&[u8]> for ServerInfoResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(control_body(bytes, CCREP_SERVER_INFO)?);
+        Ok(Self {
+            address: bytestr::to_unicode(reader.read_string()?),
+            hostname: bytestr::to_unicode(reader.read_string()?),
+            map: bytestr::to_unicode(reader.read_string()?),
+            player_count: reader.read_u8()?,
+            max_players: reader.read_u8()?,
+            protocol_version: reader.read_u8()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct PlayerInfoResponse {
+    pub number: u8,
+    pub name: String,
+    pub colors: i32,
+    pub frags: i32,
+    /// Seconds since connect.
+    pub connect_time: i32,
+    pub address: String,
+}
+
+impl TryFrom<&[u8]> for PlayerInfoResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(control_body(bytes, CCREP_PLAYER_INFO)?);
+        Ok(Self {
+            number: reader.read_u8()?,
+            name: bytestr::to_unicode(reader.read_string()?),
+            colors: reader.read_i32()?,
+            frags: reader.read_i32()?,
+            connect_time: reader.read_i32()?,
+            address: bytestr::to_unicode(reader.read_string().unwrap_or_default()),
+        })
+    }
+}
+
+impl From<&PlayerInfoResponse> for QuakeClient {
+    fn from(player: &PlayerInfoResponse) -> Self {
+        Self {
+            id: player.number as u32,
+            name: player.name.clone(),
+            frags: player.frags,
+            time: (player.connect_time.max(0) / 60) as u32,
+            top_color: ((player.colors >> 4) & 0x0f) as u8,
+            bottom_color: (player.colors & 0x0f) as u8,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RuleInfoResponse {
+    /// Rule name and value, `None` when there are no more rules.
+    pub rule: Option<(String, String)>,
+}
+
+impl TryFrom<&[u8]> for RuleInfoResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(control_body(bytes, CCREP_RULE_INFO)?);
+
+        if reader.is_empty() {
+            return Ok(Self { rule: None });
+        }
+
+        let name = bytestr::to_unicode(reader.read_string()?);
+        let value = bytestr::to_unicode(reader.read_string().unwrap_or_default());
+        Ok(Self {
+            rule: Some((name, value)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use tokio::net::UdpSocket;
+
+    fn reply(command: u8, payload: &[u8]) -> Vec<u8> {
+        control_packet(command, payload)
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[test]
+    fn test_control_packet() {
+        assert_eq!(
+            control_packet(CCREQ_SERVER_INFO, b"QUAKE\0\x03"),
+            b"\x80\x00\x00\x0c\x02QUAKE\0\x03".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_server_info_try_from() -> Result<()> {
+        assert_eq!(
+            ServerInfoResponse::try_from([0].as_slice())
+                .unwrap_err()
+                .to_string(),
+            "Invalid header"
+        );
+        assert_eq!(
+            ServerInfoResponse::try_from(reply(CCREP_PLAYER_INFO, b"").as_slice())
+                .unwrap_err()
+                .to_string(),
+            "Invalid header"
+        );
+        assert_eq!(
+            ServerInfoResponse::try_from(
+                reply(
+                    CCREP_SERVER_INFO,
+                    b"1.2.3.4:26000\0Quake\0dm4\0\x02\x10\x03"
+                )
+                .as_slice()
+            )?,
+            ServerInfoResponse {
+                address: "1.2.3.4:26000".to_string(),
+                hostname: "Quake".to_string(),
+                map: "dm4".to_string(),
+                player_count: 2,
+                max_players: 16,
+                protocol_version: 3,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_player_info_try_from() -> Result<()> {
+        let bytes = reply(
+            CCREP_PLAYER_INFO,
+            b"\x01XantoM\0\x4d\x00\x00\x00\x0c\x00\x00\x00\x2c\x01\x00\x001.2.3.4:1234\0",
+        );
+        let player = PlayerInfoResponse::try_from(bytes.as_slice())?;
+        assert_eq!(
+            player,
+            PlayerInfoResponse {
+                number: 1,
+                name: "XantoM".to_string(),
+                colors: 0x4d,
+                frags: 12,
+                connect_time: 300,
+                address: "1.2.3.4:1234".to_string(),
+            }
+        );
+        assert_eq!(
+            QuakeClient::from(&player),
+            QuakeClient {
+                id: 1,
+                name: "XantoM".to_string(),
+                frags: 12,
+                time: 5,
+                top_color: 4,
+                bottom_color: 13,
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_info_try_from() -> Result<()> {
+        assert_eq!(
+            RuleInfoResponse::try_from(reply(CCREP_RULE_INFO, b"teamplay\x001\0").as_slice())?,
+            RuleInfoResponse {
+                rule: Some(("teamplay".to_string(), "1".to_string()))
+            }
+        );
+        assert_eq!(
+            RuleInfoResponse::try_from(reply(CCREP_RULE_INFO, b"").as_slice())?,
+            RuleInfoResponse { rule: None }
+        );
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_query() -> Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+
+        tokio::spawn(async move {
+            let mut buf = [0; 1024];
+            while let Ok((len, from)) = server.recv_from(&mut buf).await {
+                let response = match (buf[4], &buf[5..len]) {
+                    (CCREQ_SERVER_INFO, _) => reply(
+                        CCREP_SERVER_INFO,
+                        b"127.0.0.1:26000\0NQ server\0e1m1\0\x01\x08\x03",
+                    ),
+                    (CCREQ_PLAYER_INFO, [0]) => reply(
+                        CCREP_PLAYER_INFO,
+                        b"\x00player\0\x00\x00\x00\x00\x05\x00\x00\x00\x3c\x00\x00\x00\0",
+                    ),
+                    (CCREQ_RULE_INFO, b"\0") => reply(CCREP_RULE_INFO, b"deathmatch\x001\0"),
+                    (CCREQ_RULE_INFO, b"deathmatch\0") => {
+                        reply(CCREP_RULE_INFO, b"pq_fullpitch\x000\0")
+                    }
+                    (CCREQ_RULE_INFO, _) => reply(CCREP_RULE_INFO, b""),
+                    _ => continue,
+                };
+                let _ = server.send_to(&response, from).await;
+            }
+        });
+
+        let res = query(&address, Duration::from_millis(200)).await?;
+        assert_eq!(res.settings.hostname, Some("NQ server".to_string()));
+        assert_eq!(res.settings.map, Some("e1m1".to_string()));
+        assert_eq!(res.settings.maxclients, Some(8));
+        assert_eq!(res.settings.deathmatch, Some(1));
+        assert_eq!(res.clients.len(), 1);
+        assert_eq!(res.clients[0].name, "player");
+        assert_eq!(res.clients[0].frags, 5);
+        assert_eq!(res.rules.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::error::{Error, Result},
+    crate::net_extra::{self, resolve},
+    crate::rt,
+    std::net::SocketAddr,
+    std::time::Instant,
+};
+
+/// Measure round-trip time using an A2A ping packet (`k`, answered with `l`).
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn ping(address: &str, timeout: Duration) -> Result<Duration> {
+    let socket_address = resolve(address).await?;
+    send_probe(socket_address, &ProbeKind::Ping, timeout).await
+}
+
+/// Measure round-trip time of a status request.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn status_rtt(address: &str, timeout: Duration) -> Result<Duration> {
+    let socket_address = resolve(address).await?;
+    send_probe(socket_address, &ProbeKind::Status, timeout).await
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ProbeKind {
+    /// A2A ping (`\xff\xff\xff\xffk`), answered with `l`.
+    #[default]
+    Ping,
+    /// Serverinfo only status request (`status 1`), answered with `n`.
+    Status,
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+impl ProbeKind {
+    fn message(&self) -> &'static [u8] {
+        match self {
+            ProbeKind::Ping => b"\xff\xff\xff\xffk",
+            ProbeKind::Status => b"\xff\xff\xff\xffstatus 1",
+        }
+    }
+
+    fn response_header(&self) -> &'static [u8] {
+        match self {
+            ProbeKind::Ping => b"\xff\xff\xff\xffl",
+            ProbeKind::Status => b"\xff\xff\xff\xffn",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProbeOptions {
+    pub kind: ProbeKind,
+    /// Number of probes to send.
+    pub count: u32,
+    /// Delay between probes.
+    pub interval: Duration,
+    /// Timeout for each probe.
+    pub timeout: Duration,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        Self {
+            kind: ProbeKind::Ping,
+            count: 5,
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PingStats {
+    pub sent: u32,
+    pub received: u32,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    /// Mean difference between consecutive round-trip times.
+    pub jitter: Duration,
+    /// Share of probes without a response, 0.0 - 1.0.
+    pub packet_loss: f32,
+}
+
+impl PingStats {
+    pub fn from_samples(sent: u32, samples: &[Duration]) -> Self {
+        let received = samples.len() as u32;
+        let packet_loss = match sent {
+            0 => 0.0,
+            _ => (sent - received.min(sent)) as f32 / sent as f32,
+        };
+
+        if samples.is_empty() {
+            return Self {
+                sent,
+                packet_loss,
+                ..Default::default()
+            };
+        }
+
+        let min = samples.iter().min().copied().unwrap_or_default();
+        let max = samples.iter().max().copied().unwrap_or_default();
+        let avg = samples.iter().sum::<Duration>() / received;
+        let jitter = match samples.len() {
+            0 | 1 => Duration::ZERO,
+            len => {
+                let diff_sum: Duration = samples.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+                diff_sum / (len as u32 - 1)
+            }
+        };
+
+        Self {
+            sent,
+            received,
+            min,
+            avg,
+            max,
+            jitter,
+            packet_loss,
+        }
+    }
+}
+
+/// Send several probes and collect round-trip statistics.
+///
+/// Lost probes are counted as packet loss, an error is only returned if the
+/// address can not be resolved.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn probe(address: &str, options: &ProbeOptions) -> Result<PingStats> {
+    let socket_address = resolve(address).await?;
+    let mut samples: Vec<Duration> = vec![];
+
+    for i in 0..options.count {
+        if i > 0 {
+            rt::sleep(options.interval).await;
+        }
+        if let Ok(rtt) = send_probe(socket_address, &options.kind, options.timeout).await {
+            samples.push(rtt);
+        }
+    }
+
+    Ok(PingStats::from_samples(options.count, &samples))
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+async fn send_probe(address: SocketAddr, kind: &ProbeKind, timeout: Duration) -> Result<Duration> {
+    let options = net_extra::ReadOptions {
+        timeout,
+        buffer_size: 64 * 1024, // 64 kb
+    };
+    let start = Instant::now();
+    let response = net_extra::send_and_receive_to(address, kind.message(), options).await?;
+    let rtt = start.elapsed();
+
+    if !response.starts_with(kind.response_header()) {
+        return Err(Error::InvalidHeader);
+    }
+
+    Ok(rtt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use {anyhow::Result, tokio::net::UdpSocket};
+
+    #[test]
+    fn test_from_samples() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(
+            PingStats::from_samples(4, &[ms(20), ms(30), ms(25)]),
+            PingStats {
+                sent: 4,
+                received: 3,
+                min: ms(20),
+                avg: ms(25),
+                max: ms(30),
+                jitter: Duration::from_micros(7500),
+                packet_loss: 0.25,
+            }
+        );
+        assert_eq!(
+            PingStats::from_samples(2, &[]),
+            PingStats {
+                sent: 2,
+                packet_loss: 1.0,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_probe() -> Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+
+        // answers every request with a ping reply
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            while let Ok((_, from)) = server.recv_from(&mut buf).await {
+                let _ = server.send_to(b"\xff\xff\xff\xffl", from).await;
+            }
+        });
+
+        let options = ProbeOptions {
+            count: 3,
+            interval: Duration::from_millis(1),
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let stats = probe(&address, &options).await?;
+        assert_eq!(stats.sent, 3);
+        assert_eq!(stats.received, 3);
+        assert_eq!(stats.packet_loss, 0.0);
+        assert!(stats.min <= stats.avg && stats.avg <= stats.max);
+
+        let options = ProbeOptions {
+            kind: ProbeKind::Status,
+            count: 2,
+            ..options
+        };
+        let stats = probe(&address, &options).await?;
+        assert_eq!(stats.received, 0);
+        assert_eq!(stats.packet_loss, 1.0);
+
+        let res = status_rtt(&address, Duration::from_millis(200)).await;
+        assert!(matches!(res, Err(Error::InvalidHeader)));
+        Ok(())
+    }
+}
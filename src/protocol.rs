@@ -0,0 +1,150 @@
+//! Request encoders and response decoders, without any IO.
+//!
+//! Use with a [`crate::transport::Transport`] or any other way of sending and
+//! receiving datagrams:
+//!
+//! ```
+//! use serverstat::protocol::{Query, StatusQuery};
+//!
+//! let query = StatusQuery::default();
+//! assert_eq!(query.encode(), b"\xff\xff\xff\xffstatus 119".to_vec());
+//!
+//! let res = query.decode(b"\xff\xff\xff\xffn\\hostname\\foo\n").unwrap();
+//! assert_eq!(res.value.settings.hostname, Some("foo".to_string()));
+//! ```
+use crate::error::Result;
+use crate::master::MasterResponse;
+use crate::status_flags::StatusFlags;
+use crate::svc_challenge::ChallengeResponse;
+use crate::svc_qtvusers::QtvusersResponse;
+use crate::svc_status::{Parsed, StatusResponse};
+
+/// A request and the decoder of its response.
+pub trait Query {
+    type Response;
+
+    fn encode(&self) -> Vec<u8>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Response>;
+}
+
+/// `status` request, see [`StatusResponse::parse_lenient`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StatusQuery {
+    pub flags: StatusFlags,
+}
+
+impl StatusQuery {
+    pub fn new(flags: StatusFlags) -> Self {
+        Self { flags }
+    }
+}
+
+impl Default for StatusQuery {
+    fn default() -> Self {
+        Self::new(StatusFlags::FULL)
+    }
+}
+
+impl Query for StatusQuery {
+    type Response = Parsed<StatusResponse>;
+
+    fn encode(&self) -> Vec<u8> {
+        self.flags.message()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Response> {
+        StatusResponse::parse_lenient(bytes, self.flags)
+    }
+}
+
+/// `qtvusers` request, names of the clients watching the QTV stream of a server.
+///
+/// see: https://github.com/QW-Group/mvdsv/blob/master/src/sv_demo_qtv.c#L1379
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QtvusersQuery;
+
+impl Query for QtvusersQuery {
+    type Response = QtvusersResponse;
+
+    fn encode(&self) -> Vec<u8> {
+        b"\xff\xff\xff\xffqtvusers".to_vec()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Response> {
+        QtvusersResponse::try_from(bytes)
+    }
+}
+
+/// `getchallenge` request.
+///
+/// see: https://github.com/QW-Group/mvdsv/blob/master/src/sv_main.c (SVC_GetChallenge)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ChallengeQuery;
+
+impl Query for ChallengeQuery {
+    type Response = ChallengeResponse;
+
+    fn encode(&self) -> Vec<u8> {
+        b"\xff\xff\xff\xffgetchallenge\n".to_vec()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Response> {
+        ChallengeResponse::try_from(bytes)
+    }
+}
+
+/// Server list request to a master server.
+///
+/// see: https://github.com/QW-Group/mvdsv/blob/master/src/qwsvdef.h (C2M/M2C)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MasterQuery;
+
+impl Query for MasterQuery {
+    type Response = MasterResponse;
+
+    fn encode(&self) -> Vec<u8> {
+        b"c\n".to_vec()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Self::Response> {
+        MasterResponse::try_from(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            StatusQuery::new(StatusFlags::OLDSTYLE).encode(),
+            b"\xff\xff\xff\xffstatus".to_vec()
+        );
+        assert_eq!(QtvusersQuery.encode(), b"\xff\xff\xff\xffqtvusers".to_vec());
+        assert_eq!(
+            ChallengeQuery.encode(),
+            b"\xff\xff\xff\xffgetchallenge\n".to_vec()
+        );
+        assert_eq!(MasterQuery.encode(), b"c\n".to_vec());
+    }
+
+    #[test]
+    fn test_decode() -> Result<()> {
+        let res = StatusQuery::new(StatusFlags::PLAYERS)
+            .decode(b"\xff\xff\xff\xffn63 43 41 25 \"XantoM\" \"\" 4 4\n63 x\n")?;
+        assert_eq!(res.value.clients.len(), 1);
+        assert_eq!(res.warnings.len(), 1);
+
+        let res = QtvusersQuery.decode(b"\xff\xff\xff\xffnqtvusers 2 \"XantoM\"\n")?;
+        assert_eq!(res.client_names, vec!["XantoM".to_string()]);
+
+        assert_eq!(
+            MasterQuery.decode(b"foo").unwrap_err().to_string(),
+            "Invalid header"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+/// Protocol ids used as tags in the challenge response, followed by extension bits.
+pub const PROTOCOL_VERSION_FTE: u32 = u32::from_le_bytes(*b"FTEX");
+pub const PROTOCOL_VERSION_FTE2: u32 = u32::from_le_bytes(*b"FTE2");
+pub const PROTOCOL_VERSION_MVD1: u32 = u32::from_le_bytes(*b"MVD1");
+pub const PROTOCOL_VERSION_FRAGMENT: u32 = u32::from_le_bytes(*b"FRAG");
+
+// FTE extensions
+pub const FTE_PEXT_TRANS: u32 = 0x0000_0008;
+pub const FTE_PEXT_ACCURATETIMINGS: u32 = 0x0000_0040;
+pub const FTE_PEXT_HLBSP: u32 = 0x0000_0200;
+pub const FTE_PEXT_MODELDBL: u32 = 0x0000_1000;
+pub const FTE_PEXT_ENTITYDBL: u32 = 0x0000_2000;
+pub const FTE_PEXT_ENTITYDBL2: u32 = 0x0000_4000;
+pub const FTE_PEXT_FLOATCOORDS: u32 = 0x0000_8000;
+pub const FTE_PEXT_SPAWNSTATIC2: u32 = 0x0040_0000;
+pub const FTE_PEXT_256PACKETENTITIES: u32 = 0x0100_0000;
+pub const FTE_PEXT_CHUNKEDDOWNLOADS: u32 = 0x2000_0000;
+pub const FTE_PEXT_CSQC: u32 = 0x4000_0000;
+
+// FTE2 extensions
+pub const FTE_PEXT2_VOICECHAT: u32 = 0x0000_0002;
+pub const FTE_PEXT2_REPLACEMENTDELTAS: u32 = 0x0000_0008;
+pub const FTE_PEXT2_MAXPLAYERS: u32 = 0x0000_0010;
+
+// MVDSV extensions
+pub const MVD_PEXT1_FLOATCOORDS: u32 = 0x0000_0001;
+pub const MVD_PEXT1_HIGHLAGTELEPORT: u32 = 0x0000_0002;
+pub const MVD_PEXT1_SERVERSIDEWEAPON: u32 = 0x0000_0004;
+pub const MVD_PEXT1_DEBUG_WEAPON: u32 = 0x0000_0008;
+pub const MVD_PEXT1_DEBUG_ANTILAG: u32 = 0x0000_0010;
+pub const MVD_PEXT1_HIDDEN_MESSAGES: u32 = 0x0000_0020;
+pub const MVD_PEXT1_SERVERSIDEWEAPON2: u32 = 0x0000_0040;
+
+// ZQuake extensions (serverinfo *z_ext)
+pub const Z_EXT_PM_TYPE: u32 = 0x0000_0001;
+pub const Z_EXT_PM_TYPE_NEW: u32 = 0x0000_0002;
+pub const Z_EXT_VIEWHEIGHT: u32 = 0x0000_0004;
+pub const Z_EXT_SERVERTIME: u32 = 0x0000_0008;
+pub const Z_EXT_PITCHLIMITS: u32 = 0x0000_0010;
+pub const Z_EXT_JOIN_OBSERVE: u32 = 0x0000_0020;
+pub const Z_EXT_PF_ONGROUND: u32 = 0x0000_0040;
+pub const Z_EXT_VWEP: u32 = 0x0000_0080;
+pub const Z_EXT_PF_SOLID: u32 = 0x0000_0100;
+
+/// Protocol extensions advertised by a server.
+///
+/// `None` means the server did not advertise the extension set at all.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct ProtocolExtensions {
+    pub fte: Option<u32>,
+    pub fte2: Option<u32>,
+    pub mvd: Option<u32>,
+    pub z_ext: Option<u32>,
+    /// Max packet size when the server supports fragmented packets.
+    pub fragment_mtu: Option<u32>,
+}
+
+impl ProtocolExtensions {
+    /// Set extension bits from a protocol tag, unknown tags are ignored.
+    pub fn set(&mut self, protocol: u32, value: u32) {
+        match protocol {
+            PROTOCOL_VERSION_FTE => self.fte = Some(value),
+            PROTOCOL_VERSION_FTE2 => self.fte2 = Some(value),
+            PROTOCOL_VERSION_MVD1 => self.mvd = Some(value),
+            PROTOCOL_VERSION_FRAGMENT => self.fragment_mtu = Some(value),
+            _ => {}
+        }
+    }
+
+    pub fn has_fte(&self, bits: u32) -> bool {
+        self.fte.is_some_and(|v| v & bits == bits)
+    }
+
+    pub fn has_fte2(&self, bits: u32) -> bool {
+        self.fte2.is_some_and(|v| v & bits == bits)
+    }
+
+    pub fn has_mvd(&self, bits: u32) -> bool {
+        self.mvd.is_some_and(|v| v & bits == bits)
+    }
+
+    pub fn has_z_ext(&self, bits: u32) -> bool {
+        self.z_ext.is_some_and(|v| v & bits == bits)
+    }
+
+    pub fn supports_chunked_downloads(&self) -> bool {
+        self.has_fte(FTE_PEXT_CHUNKEDDOWNLOADS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_protocol_versions() {
+        assert_eq!(PROTOCOL_VERSION_FTE, 0x58455446);
+        assert_eq!(PROTOCOL_VERSION_FTE2, 0x32455446);
+        assert_eq!(PROTOCOL_VERSION_MVD1, 0x3144564d);
+    }
+
+    #[test]
+    fn test_set_and_has() {
+        let mut ext = ProtocolExtensions::default();
+        assert!(!ext.supports_chunked_downloads());
+
+        ext.set(
+            PROTOCOL_VERSION_FTE,
+            FTE_PEXT_CHUNKEDDOWNLOADS | FTE_PEXT_TRANS,
+        );
+        ext.set(PROTOCOL_VERSION_MVD1, MVD_PEXT1_FLOATCOORDS);
+        ext.set(0, 1);
+
+        assert!(ext.supports_chunked_downloads());
+        assert!(ext.has_fte(FTE_PEXT_TRANS));
+        assert!(!ext.has_fte(FTE_PEXT_CSQC));
+        assert!(ext.has_mvd(MVD_PEXT1_FLOATCOORDS));
+        assert!(!ext.has_fte2(FTE_PEXT2_VOICECHAT));
+        assert_eq!(ext.fte2, None);
+        assert_eq!(ext.fragment_mtu, None);
+    }
+}
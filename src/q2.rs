@@ -0,0 +1,148 @@
+//! Quake 2 server queries.
+use crate::client::QuakeClient;
+use crate::error::{Error, Result, parse_field};
+use crate::tokenize::tokenize;
+use quake_serverinfo::Settings;
+use quake_text::bytestr;
+use std::io::{BufRead, Cursor};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {crate::net_extra, std::time::Duration};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn status(address: &str, timeout: Duration) -> Result<Q2StatusResponse> {
+    let response_bytes = {
+        let message = b"\xff\xff\xff\xffstatus\n".to_vec();
+        let options = net_extra::ReadOptions {
+            timeout,
+            buffer_size: 64 * 1024, // 64 kb
+        };
+        net_extra::send_and_receive(address, &message, options).await?
+    };
+    Q2StatusResponse::try_from(response_bytes.as_slice())
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Q2StatusResponse {
+    pub settings: Settings,
+    pub clients: Vec<QuakeClient>,
+}
+
+impl TryFrom<&[u8]> for Q2StatusResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        // validate header
+        let header = b"\xff\xff\xff\xffprint\n".to_vec();
+
+        if !bytes.starts_with(&header) {
+            return Err(Error::InvalidHeader);
+        }
+
+        // parse body
+        let body = &bytes[header.len()..];
+        let rows: Vec<Vec<u8>> = Cursor::new(body).split(10).filter_map(|l| l.ok()).collect();
+
+        const MIN_SERVERINFO_LENGTH: usize = "hostname\\x".len();
+
+        if rows.is_empty() || rows[0].len() < MIN_SERVERINFO_LENGTH {
+            return Err(Error::InvalidBody);
+        }
+
+        // parse serverinfo, q2 uses "mapname" instead of "map"
+        let mut info = quake_infostring::to_hashmap(&bytestr::to_unicode(&rows[0]));
+        if let Some(mapname) = info.get("mapname").cloned() {
+            info.entry("map".to_string()).or_insert(mapname);
+        }
+        let settings = Settings::from(&info);
+
+        // parse clients: frags ping "name"
+        let clients = rows[1..]
+            .iter()
+            .enumerate()
+            .filter_map(|(index, row)| client_from_row(index, row).ok())
+            .collect();
+
+        Ok(Self { settings, clients })
+    }
+}
+
+fn client_from_row(index: usize, row: &[u8]) -> Result<QuakeClient> {
+    let row = bytestr::to_unicode(row);
+    let parts = tokenize(&row);
+
+    if parts.len() < 3 {
+        return Err(Error::parse("client", &row));
+    }
+
+    let frags: i32 = parse_field(&parts[0], "frags", &row)?;
+    let ping: i32 = parse_field(&parts[1], "ping", &row)?;
+
+    Ok(QuakeClient {
+        id: index as u32,
+        name: parts[2].to_string(),
+        frags,
+        ping: ping.unsigned_abs(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        // invalid
+        {
+            let res = Q2StatusResponse::try_from([0].as_slice());
+            assert_eq!(res.unwrap_err().to_string(), "Invalid header".to_string());
+        }
+        {
+            let res = Q2StatusResponse::try_from(b"\xff\xff\xff\xffprint\n".as_slice());
+            assert_eq!(res.unwrap_err().to_string(), "Invalid body".to_string());
+        }
+
+        // with clients
+        {
+            let bytes = b"\xff\xff\xff\xffprint\n\\mapname\\q2dm1\\hostname\\Q2 server\\maxclients\\16\\version\\q2pro r1504\\timelimit\\20\n12 34 \"Player one\"\n-1 0 \"bot\"\n";
+            let res = Q2StatusResponse::try_from(bytes.as_slice())?;
+
+            assert_eq!(res.settings.hostname, Some("Q2 server".to_string()));
+            assert_eq!(res.settings.map, Some("q2dm1".to_string()));
+            assert_eq!(res.settings.maxclients, Some(16));
+            assert_eq!(res.settings.timelimit, Some(20));
+            assert_eq!(
+                res.clients,
+                vec![
+                    QuakeClient {
+                        id: 0,
+                        name: "Player one".to_string(),
+                        frags: 12,
+                        ping: 34,
+                        ..Default::default()
+                    },
+                    QuakeClient {
+                        id: 1,
+                        name: "bot".to_string(),
+                        frags: -1,
+                        ping: 0,
+                        ..Default::default()
+                    },
+                ]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serverinfo_trailing_separator() -> Result<()> {
+        let bytes = b"\xff\xff\xff\xffprint\n\\hostname\\Q2 server\\mapname\\q2dm1\\\n";
+        let res = Q2StatusResponse::try_from(bytes.as_slice())?;
+        assert_eq!(res.settings.hostname, Some("Q2 server".to_string()));
+        assert_eq!(res.settings.map, Some("q2dm1".to_string()));
+        Ok(())
+    }
+}
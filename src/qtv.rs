@@ -2,8 +2,14 @@ use quake_serverinfo::Settings;
 use quake_text::bytestr::to_unicode;
 
 use crate::client::QuakeClient;
+use crate::error::{Error, parse_field};
 use crate::server::QuakeServer;
 use crate::tokenize;
+use anyhow::Result;
+use std::borrow::Cow;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {crate::qtv_tcp, std::time::Duration};
 
 use crate::hostport::Hostport;
 #[cfg(feature = "json")]
@@ -14,13 +20,35 @@ use serde::{Deserialize, Serialize};
 pub struct QtvServer {
     pub settings: QtvSettings,
     pub clients: Vec<QtvClient>,
+    pub streams: Vec<QtvStream>,
+}
+
+impl QtvServer {
+    /// Fetch the stream sources of the proxy at the given address (TCP).
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn fetch_streams(&mut self, address: &str, timeout: Duration) -> Result<()> {
+        self.streams = qtv_tcp::streams(address, timeout).await?;
+        Ok(())
+    }
+
+    /// Fetch the live streams of the proxy at the given address (HTTP).
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[cfg(feature = "http")]
+    pub async fn fetch_nowplaying(&mut self, address: &str, timeout: Duration) -> Result<()> {
+        self.streams = crate::qtv_http::nowplaying(address, timeout).await?;
+        Ok(())
+    }
 }
 
 impl From<&QuakeServer> for QtvServer {
     fn from(server: &QuakeServer) -> Self {
         let settings = QtvSettings::from(&server.settings);
         let clients = server.clients.iter().map(QtvClient::from).collect();
-        Self { settings, clients }
+        Self {
+            settings,
+            clients,
+            streams: vec![],
+        }
     }
 }
 
@@ -78,11 +106,20 @@ impl QtvStream {
 }
 
 impl TryFrom<&[u8]> for QtvStream {
-    type Error = anyhow::Error;
+    type Error = Error;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        let parts: Vec<String> = tokenize::tokenize(to_unicode(bytes).as_str());
-        let id: u32 = parts[1].parse()?;
+        let row = to_unicode(bytes);
+        let parts: Vec<Cow<str>> = tokenize::tokens(row.as_str()).collect();
+
+        // qtv id name url client_count
+        const MIN_COLUMNS: usize = 5;
+
+        if parts.len() < MIN_COLUMNS {
+            return Err(Error::parse("qtv stream", &row));
+        }
+
+        let id: u32 = parse_field(&parts[1], "id", &row)?;
         let name = parts[2].to_string();
         let url = parts[3].to_string();
         let (number, address) = match url.split_once('@') {
@@ -92,8 +129,9 @@ impl TryFrom<&[u8]> for QtvStream {
             }
             None => (0, url.clone()),
         };
-        let client_count: u32 = parts[4].parse()?;
-        let address = Hostport::try_from(address.as_str())?;
+        let client_count: u32 = parse_field(&parts[4], "client count", &row)?;
+        let address =
+            Hostport::try_from(address.as_str()).map_err(|_| Error::parse("address", &row))?;
 
         Ok(Self {
             id,
@@ -109,14 +147,16 @@ impl TryFrom<&[u8]> for QtvStream {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anyhow::Result;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use crate::mock::MockServer;
     use pretty_assertions::assert_eq;
-    use std::time::Duration;
 
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
     #[tokio::test]
     async fn test_from_gameserver() -> Result<()> {
+        let mock = MockServer::qtv("QUAKE.SE KTX Qtv").spawn()?;
         let server =
-            QuakeServer::try_from_address("quake.se:28000", Duration::from_secs_f32(0.5)).await?;
+            QuakeServer::try_from_address(&mock.address(), Duration::from_secs_f32(0.5)).await?;
         assert_eq!(
             QtvServer::from(&server).settings.hostname,
             "QUAKE.SE KTX Qtv"
@@ -153,6 +193,18 @@ mod tests {
                 client_names: vec![],
             }
         );
+        assert_eq!(
+            QtvStream::try_from(br#"qtv 1 "dm6.uk Qtv (7)""#.as_ref())
+                .unwrap_err()
+                .to_string(),
+            "Invalid qtv stream"
+        );
+        assert_eq!(
+            QtvStream::try_from(br#"qtv 1 "dm6.uk Qtv (7)" "7@dm6.uk" 4"#.as_ref())
+                .unwrap_err()
+                .to_string(),
+            "Invalid address"
+        );
         Ok(())
     }
 
@@ -0,0 +1,239 @@
+//! QTV proxy HTTP interface (`/nowplaying`, `/demos`).
+use crate::hostport::Hostport;
+use crate::qtv::QtvStream;
+use anyhow::{Result, anyhow as e};
+use std::time::Duration;
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+pub struct QtvDemo {
+    pub name: String,
+    /// Size in bytes (as listed, kB precision).
+    pub size: u64,
+    pub date: Option<String>,
+}
+
+impl QtvDemo {
+    pub fn url(&self, address: &Hostport) -> String {
+        format!("http://{}/dl/demos/{}", address, self.name)
+    }
+}
+
+/// Get the live streams of a QTV proxy.
+pub async fn nowplaying(address: &str, timeout: Duration) -> Result<Vec<QtvStream>> {
+    let hostport = Hostport::try_from(address)?;
+    let html = get(&hostport, "/nowplaying", timeout).await?;
+    Ok(parse_nowplaying(&html, &hostport))
+}
+
+/// Get the demos available for download on a QTV proxy.
+pub async fn demos(address: &str, timeout: Duration) -> Result<Vec<QtvDemo>> {
+    let hostport = Hostport::try_from(address)?;
+    let html = get(&hostport, "/demos", timeout).await?;
+    Ok(parse_demos(&html))
+}
+
+async fn get(address: &Hostport, path: &str, timeout: Duration) -> Result<String> {
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+    let res = client
+        .get(format!("http://{}{}", address, path))
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        return Err(e!("Invalid response status: {}", res.status()));
+    }
+
+    Ok(res.text().await?)
+}
+
+/// Parse the `/nowplaying` page, one `<dt>` entry per stream followed by a player list.
+pub fn parse_nowplaying(html: &str, address: &Hostport) -> Vec<QtvStream> {
+    html.split("<dt>")
+        .skip(1)
+        .filter_map(|entry| {
+            let (title, players) = entry.split_once("</dt>").unwrap_or((entry, ""));
+            let (_, sid) = title.split_once("watch.qtv?sid=")?;
+            let id: u32 = sid
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .ok()?;
+            let name = strip_tags(title.split("<span").next().unwrap_or_default());
+
+            let players = players.split("<dt>").next().unwrap_or_default();
+            let client_names: Vec<String> = players
+                .split("<li")
+                .skip(1)
+                .filter_map(|li| {
+                    let (_, text) = li.split_once('>')?;
+                    let (text, _) = text.split_once("</li>")?;
+                    Some(strip_tags(text))
+                })
+                .collect();
+
+            Some(QtvStream {
+                id,
+                name,
+                number: id,
+                address: address.clone(),
+                client_count: client_names.len() as u32,
+                client_names,
+            })
+        })
+        .collect()
+}
+
+/// Parse the `/demos` page, one table row per demo.
+pub fn parse_demos(html: &str) -> Vec<QtvDemo> {
+    html.split("<tr")
+        .skip(1)
+        .filter_map(|row| {
+            let name = td_text(row, "name")?;
+            let size = td_text(row, "size")?;
+            let size = match size.split_once(' ') {
+                Some((value, "kB")) => value.parse::<u64>().ok()? * 1024,
+                Some((value, "MB")) => value.parse::<u64>().ok()? * 1024 * 1024,
+                _ => size.parse().ok()?,
+            };
+            let date = td_text(row, "date");
+            Some(QtvDemo { name, size, date })
+        })
+        .collect()
+}
+
+fn td_text(row: &str, class: &str) -> Option<String> {
+    let (_, cell) = row.split_once(&format!("<td class=\"{}\">", class))?;
+    let (cell, _) = cell.split_once("</td>")?;
+    Some(strip_tags(cell))
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qtv::QtvServer;
+    use pretty_assertions::assert_eq;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const NOWPLAYING: &str = include_str!("../tests/fixtures/qtv_nowplaying.html");
+    const DEMOS: &str = include_str!("../tests/fixtures/qtv_demos.html");
+
+    #[test]
+    fn test_parse_nowplaying() {
+        let address = Hostport::new("qtv.quake.se".to_string(), 28000);
+        assert_eq!(parse_nowplaying("", &address), vec![]);
+        assert_eq!(
+            parse_nowplaying(NOWPLAYING, &address),
+            vec![
+                QtvStream {
+                    id: 1,
+                    name: "tcp:quake.se:28501 (QUAKE.SE KTX:28501: dm3)".to_string(),
+                    number: 1,
+                    address: address.clone(),
+                    client_count: 2,
+                    client_names: vec!["XantoM".to_string(), "bps & co".to_string()],
+                },
+                QtvStream {
+                    id: 4,
+                    name: "tcp:quake.se:28502 (QUAKE.SE KTX:28502: aerowalk)".to_string(),
+                    number: 4,
+                    address: address.clone(),
+                    client_count: 0,
+                    client_names: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_demos() {
+        assert_eq!(parse_demos(""), vec![]);
+        assert_eq!(
+            parse_demos(DEMOS),
+            vec![
+                QtvDemo {
+                    name: "duel_milton_vs_bps[dm6]081024-2031.mvd".to_string(),
+                    size: 1234 * 1024,
+                    date: Some("2024-10-08 20:31".to_string()),
+                },
+                QtvDemo {
+                    name: "4on4_red_vs_blue[dm3]081024-2155.mvd".to_string(),
+                    size: 5678 * 1024,
+                    date: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_demo_url() {
+        let demo = QtvDemo {
+            name: "duel.mvd".to_string(),
+            ..Default::default()
+        };
+        let address = Hostport::new("qtv.quake.se".to_string(), 28000);
+        assert_eq!(
+            demo.url(&address),
+            "http://qtv.quake.se:28000/dl/demos/duel.mvd"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nowplaying_and_demos() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let len = socket.read(&mut buf).await?;
+                let request = String::from_utf8_lossy(&buf[..len]);
+                let body = match request.starts_with("GET /demos ") {
+                    true => DEMOS,
+                    false => NOWPLAYING,
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let timeout = Duration::from_secs(1);
+        let mut server = QtvServer::default();
+        server.fetch_nowplaying(&address, timeout).await?;
+        assert_eq!(server.streams.len(), 2);
+        assert_eq!(server.streams[0].url(), format!("1@{}", address));
+        assert_eq!(demos(&address, timeout).await?.len(), 2);
+        Ok(())
+    }
+}
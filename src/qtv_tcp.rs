@@ -0,0 +1,329 @@
+//! QTV proxy queries over TCP.
+use crate::error::{Error, parse_field};
+use crate::hostport::Hostport;
+use crate::qtv::QtvStream;
+use anyhow::Result;
+use quake_text::bytestr;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::gameserver::GameServer,
+    crate::mvd::MvdState,
+    crate::net_extra,
+    crate::rt::{self, AsyncReadExt, AsyncWriteExt, TcpStream},
+    std::time::Duration,
+};
+
+/// A stream source of a QTV proxy.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QtvSource {
+    pub id: u32,
+    /// Source as configured on the proxy, e.g. "tcp:quake.se:28501".
+    pub server: String,
+    pub hostname: String,
+}
+
+impl QtvSource {
+    /// Stream as watched through the proxy at the given address.
+    pub fn to_stream(&self, address: &Hostport) -> QtvStream {
+        QtvStream {
+            id: self.id,
+            name: self.hostname.clone(),
+            number: self.id,
+            address: address.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Get the stream sources of a QTV proxy.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn sourcelist(address: &str, timeout: Duration) -> Result<Vec<QtvSource>> {
+    let bytes = rt::timeout(timeout, async {
+        let socket_address = net_extra::resolve(address).await?;
+        let mut stream = TcpStream::connect(socket_address).await?;
+        stream.write_all(b"QTV\nVERSION: 1\nSOURCELIST\n\n").await?;
+
+        // response ends with an empty line, proxies may keep the connection open
+        let mut bytes = vec![];
+        let mut buffer = [0; 4096];
+        loop {
+            let len = stream.read(&mut buffer).await?;
+            bytes.extend_from_slice(&buffer[..len]);
+            if len == 0 || bytes.ends_with(b"\n\n") {
+                break;
+            }
+        }
+        Ok::<Vec<u8>, anyhow::Error>(bytes)
+    })
+    .await
+    .map_err(|_| Error::Timeout)??;
+
+    Ok(SourcelistResponse::try_from(bytes.as_slice())?.sources)
+}
+
+/// Get the streams of a QTV proxy.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn streams(address: &str, timeout: Duration) -> Result<Vec<QtvStream>> {
+    let hostport = Hostport::try_from(address)?;
+    let sources = sourcelist(address, timeout).await?;
+    Ok(sources.iter().map(|s| s.to_stream(&hostport)).collect())
+}
+
+/// Build a snapshot of a match by reading the initial state of a QTV stream.
+///
+/// The stream is read until the first game frame, giving full userinfo of
+/// all clients (also for servers only visible through QTV).
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn snapshot(address: &str, source: u32, timeout: Duration) -> Result<GameServer> {
+    let hostport = Hostport::try_from(address)?;
+    let mut state = MvdState::default();
+
+    let result = rt::timeout(timeout, async {
+        let socket_address = net_extra::resolve(address).await?;
+        let mut stream = TcpStream::connect(socket_address).await?;
+        let request = format!("QTV\nVERSION: 1\nSOURCE: {}\n\n", source);
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut bytes = vec![];
+        let mut buffer = vec![0; 64 * 1024]; // 64 kb
+        let mut has_header = false;
+
+        while !(state.in_game || state.is_disconnected) {
+            let len = stream.read(&mut buffer).await?;
+            if len == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&buffer[..len]);
+
+            // header ends with an empty line, followed by mvd data
+            if !has_header && let Some(pos) = bytes.windows(2).position(|w| w == b"\n\n") {
+                validate_stream_header(&bytes[..pos + 1])?;
+                has_header = true;
+                bytes.drain(..pos + 2);
+            }
+
+            if has_header {
+                let consumed = state.parse_frames(&bytes)?;
+                bytes.drain(..consumed);
+            }
+        }
+
+        Ok::<(), anyhow::Error>(())
+    })
+    .await;
+
+    match result {
+        Ok(Err(err)) => return Err(err),
+        Err(_) if state.serverdata.is_none() => {
+            return Err(Error::Timeout.into());
+        }
+        _ if state.serverdata.is_none() => return Err(Error::InvalidBody.into()),
+        _ => {}
+    }
+
+    let mut server = state.to_gameserver();
+    server.qtv_stream = Some(QtvStream {
+        id: source,
+        name: server.settings.hostname.clone().unwrap_or_default(),
+        number: source,
+        address: hostport,
+        ..Default::default()
+    });
+    Ok(server)
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn validate_stream_header(bytes: &[u8]) -> Result<(), Error> {
+    let text = bytestr::to_unicode(bytes);
+    let mut lines = text.lines();
+
+    if !lines.next().is_some_and(|l| l.starts_with("QTVSV 1")) {
+        return Err(Error::InvalidHeader);
+    }
+
+    for line in lines {
+        if let Some(message) = line
+            .strip_prefix("PERROR:")
+            .or_else(|| line.strip_prefix("ERROR:"))
+        {
+            return Err(Error::Server(message.trim().to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SourcelistResponse {
+    pub sources: Vec<QtvSource>,
+}
+
+impl TryFrom<&[u8]> for SourcelistResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let text = bytestr::to_unicode(bytes);
+        let mut lines = text.lines();
+
+        // validate header
+        if !lines.next().is_some_and(|l| l.starts_with("QTVSV 1")) {
+            return Err(Error::InvalidHeader);
+        }
+
+        let mut sources = vec![];
+
+        for line in lines {
+            if let Some(message) = line.strip_prefix("PERROR:") {
+                return Err(Error::Server(message.trim().to_string()));
+            }
+
+            // ASOURCE: %i: %15s: %15s
+            if let Some(value) = line.strip_prefix("ASOURCE:") {
+                let mut parts = value.splitn(3, ": ").map(|p| p.trim());
+                let (Some(id), Some(server), Some(hostname)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(Error::InvalidBody);
+                };
+                sources.push(QtvSource {
+                    id: parse_field(id, "id", line)?,
+                    server: server.to_string(),
+                    hostname: hostname.to_string(),
+                });
+            }
+        }
+
+        Ok(Self { sources })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use tokio::net::TcpListener;
+
+    const SOURCELIST: &[u8] = b"QTVSV 1\nASOURCE: 1: tcp:quake.se:28501: QUAKE.SE KTX:28501\nASOURCE: 2: tcp:10.0.0.2:27500:       duel #1\n\n";
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        assert_eq!(
+            SourcelistResponse::try_from(b"foo".as_slice())
+                .unwrap_err()
+                .to_string(),
+            "Invalid header"
+        );
+        assert_eq!(
+            SourcelistResponse::try_from(b"QTVSV 1\nPERROR: Not allowed\n\n".as_slice())
+                .unwrap_err()
+                .to_string(),
+            "Not allowed"
+        );
+        assert_eq!(
+            SourcelistResponse::try_from(b"QTVSV 1\n\n".as_slice())?,
+            SourcelistResponse::default()
+        );
+        assert_eq!(
+            SourcelistResponse::try_from(SOURCELIST)?.sources,
+            vec![
+                QtvSource {
+                    id: 1,
+                    server: "tcp:quake.se:28501".to_string(),
+                    hostname: "QUAKE.SE KTX:28501".to_string(),
+                },
+                QtvSource {
+                    id: 2,
+                    server: "tcp:10.0.0.2:27500".to_string(),
+                    hostname: "duel #1".to_string(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_streams() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let mut buf = [0; 128];
+            let len = socket.read(&mut buf).await?;
+            assert_eq!(&buf[..len], b"QTV\nVERSION: 1\nSOURCELIST\n\n");
+            socket.write_all(SOURCELIST).await?;
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let streams = streams(&address, Duration::from_millis(500)).await?;
+        assert_eq!(streams.len(), 2);
+        assert_eq!(
+            streams[1],
+            QtvStream {
+                id: 2,
+                name: "duel #1".to_string(),
+                number: 2,
+                address: Hostport::try_from(address.as_str())?,
+                client_count: 0,
+                client_names: vec![],
+            }
+        );
+        assert_eq!(streams[1].url(), format!("2@{}", address));
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[test]
+    fn test_validate_stream_header() {
+        assert!(validate_stream_header(b"QTVSV 1\nBEGIN: 1\n").is_ok());
+        assert_eq!(
+            validate_stream_header(b"foo\n").unwrap_err().to_string(),
+            "Invalid header"
+        );
+        assert_eq!(
+            validate_stream_header(b"QTVSV 1\nPERROR: Source not found\n")
+                .unwrap_err()
+                .to_string(),
+            "Source not found"
+        );
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_snapshot() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let mut buf = [0; 128];
+            let len = socket.read(&mut buf).await?;
+            assert_eq!(&buf[..len], b"QTV\nVERSION: 1\nSOURCE: 3\n\n");
+
+            // split to test partial frames
+            let mvd = include_bytes!("../tests/fixtures/snapshot.mvd");
+            socket.write_all(b"QTVSV 1\nBEGIN: 3\n\n").await?;
+            socket.write_all(&mvd[..100]).await?;
+            socket.flush().await?;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            socket.write_all(&mvd[100..]).await?;
+
+            // keep the connection open, like a live stream
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let server = snapshot(&address, 3, Duration::from_millis(500)).await?;
+        assert_eq!(server.settings.map, Some("dm3".to_string()));
+        assert_eq!(server.players.len(), 2);
+        assert_eq!(server.spectators.len(), 1);
+        assert_eq!(
+            server.qtv_stream.map(|s| s.url()),
+            Some(format!("3@{}", address))
+        );
+        Ok(())
+    }
+}
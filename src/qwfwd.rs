@@ -67,14 +67,16 @@ impl From<&QuakeClient> for QwfwdClient {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anyhow::Result;
     use pretty_assertions::assert_eq;
-    use std::time::Duration;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use {crate::mock::MockServer, anyhow::Result, std::time::Duration};
 
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
     #[tokio::test]
     async fn test_from_gameserver() -> Result<()> {
+        let mock = MockServer::qwfwd("QUAKE.SE KTX QWfwd").spawn()?;
         let server =
-            QuakeServer::try_from_address("quake.se:30000", Duration::from_secs_f32(0.5)).await?;
+            QuakeServer::try_from_address(&mock.address(), Duration::from_secs_f32(0.5)).await?;
         assert_eq!(
             QwfwdServer::from(&server).settings.hostname,
             "QUAKE.SE KTX QWfwd"
@@ -0,0 +1,306 @@
+use crate::error::{Error, Result};
+use quake_text::bytestr;
+use std::time::Duration;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::tokenize::command_tokens,
+    crate::{net_extra, rt},
+    std::net::SocketAddr,
+    std::time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum RconAuth {
+    /// Password sent as is.
+    #[default]
+    Plain,
+    /// Password hashed together with a timestamp and the command, required by
+    /// servers with `sv_crypt_rcon 1`.
+    Crypt,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RconOptions {
+    pub auth: RconAuth,
+    /// Max time to wait for the first response packet.
+    pub timeout: Duration,
+    /// Output is considered complete when no packet arrived for this long.
+    pub quiet: Duration,
+    /// Max time to collect output.
+    pub max_duration: Duration,
+}
+
+impl Default for RconOptions {
+    fn default() -> Self {
+        Self {
+            auth: RconAuth::Plain,
+            timeout: Duration::from_secs(1),
+            quiet: Duration::from_millis(200),
+            max_duration: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Run a command using rcon and return its console output.
+///
+/// If the password is rejected using [`RconAuth::Plain`], the command is sent
+/// again using [`RconAuth::Crypt`] (servers with `sv_crypt_rcon 1` only accept
+/// hashed passwords).
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn rcon(
+    address: &str,
+    password: &str,
+    command: &str,
+    options: &RconOptions,
+) -> Result<String> {
+    let socket_address = net_extra::resolve(address).await?;
+
+    match send_command(socket_address, password, command, &options.auth, options).await {
+        Err(Error::BadRconPassword) if options.auth == RconAuth::Plain => {
+            let auth = RconAuth::Crypt;
+            send_command(socket_address, password, command, &auth, options).await
+        }
+        res => res,
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+async fn send_command(
+    socket_address: SocketAddr,
+    password: &str,
+    command: &str,
+    auth: &RconAuth,
+    options: &RconOptions,
+) -> Result<String> {
+    let message = match auth {
+        RconAuth::Plain => message_plain(password, command),
+        RconAuth::Crypt => message_crypt(password, command, unix_time()),
+    };
+    let socket = net_extra::bind_for(&socket_address).await?;
+    socket.send_to(&message, socket_address).await?;
+
+    let mut packets: Vec<Vec<u8>> = vec![];
+    let mut buffer = vec![0; 64 * 1024]; // 64 kb
+    let end = Instant::now() + options.max_duration;
+
+    loop {
+        let wait = match packets.is_empty() {
+            true => options.timeout,
+            false => options.quiet,
+        };
+        let wait = wait.min(end.saturating_duration_since(Instant::now()));
+
+        match rt::timeout(wait, socket.recv_from(&mut buffer)).await {
+            Ok(Ok((bytes_read, from))) if from == socket_address => {
+                packets.push(buffer[..bytes_read].to_vec());
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => return Err(err.into()),
+            Err(_) if packets.is_empty() => {
+                return Err(Error::Timeout);
+            }
+            Err(_) => break,
+        }
+    }
+
+    let output = RconResponse::try_from(packets.as_slice())?.output;
+
+    if output.starts_with("Bad rcon_password.") {
+        return Err(Error::BadRconPassword);
+    }
+
+    Ok(output)
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RconResponse {
+    pub output: String,
+}
+
+impl TryFrom<&[Vec<u8>]> for RconResponse {
+    type Error = Error;
+
+    fn try_from(packets: &[Vec<u8>]) -> Result<Self, Self::Error> {
+        let header = b"\xff\xff\xff\xffn".to_vec();
+        let mut body: Vec<u8> = vec![];
+
+        for packet in packets {
+            if !packet.starts_with(&header) {
+                return Err(Error::InvalidHeader);
+            }
+            body.extend_from_slice(&packet[header.len()..]);
+        }
+
+        let len = body.iter().position(|&b| b == 0).unwrap_or(body.len());
+        Ok(Self {
+            output: bytestr::to_unicode(&body[..len]),
+        })
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn message_plain(password: &str, command: &str) -> Vec<u8> {
+    let mut message = b"\xff\xff\xff\xff".to_vec();
+    message.extend_from_slice(format!("rcon {} {}", password, command).as_bytes());
+    message
+}
+
+/// see: https://github.com/QW-Group/mvdsv/blob/master/src/sv_main.c (Rcon_Validate)
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn message_crypt(password: &str, command: &str, time: u64) -> Vec<u8> {
+    message_hashed(password, command, &to_hex(&time.to_le_bytes()))
+}
+
+/// `rcon <sha1 of password, salt and arguments><salt> <command>`
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn message_hashed(password: &str, command: &str, salt: &str) -> Vec<u8> {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(b"rcon ");
+    hasher.update(password.as_bytes());
+    hasher.update(salt.as_bytes());
+    hasher.update(b" ");
+    for arg in command_tokens(command) {
+        hasher.update(arg.as_bytes());
+        hasher.update(b" ");
+    }
+    let digest = hasher.digest().to_string().to_uppercase();
+
+    let mut message = b"\xff\xff\xff\xffrcon ".to_vec();
+    message.extend_from_slice(digest.as_bytes());
+    message.extend_from_slice(salt.as_bytes());
+    message.push(b' ');
+    message.extend_from_slice(command.as_bytes());
+    message
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use tokio::net::UdpSocket;
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[test]
+    fn test_message_plain() {
+        assert_eq!(
+            message_plain("secret", "status"),
+            b"\xff\xff\xff\xffrcon secret status".to_vec()
+        );
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[test]
+    fn test_message_crypt() {
+        let message = message_crypt("secret", "kick 12", 0x0102030405060708);
+        let text = String::from_utf8(message[4..].to_vec()).unwrap();
+        let expected_hash = {
+            let mut hasher = sha1_smol::Sha1::new();
+            hasher.update(b"rcon secret0807060504030201 kick 12 ");
+            hasher.digest().to_string().to_uppercase()
+        };
+
+        assert_eq!(
+            text,
+            format!("rcon {}0807060504030201 kick 12", expected_hash)
+        );
+    }
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        let packets = vec![
+            b"\xff\xff\xff\xffnline 1\nli".to_vec(),
+            b"\xff\xff\xff\xffnne 2\n\0".to_vec(),
+        ];
+        assert_eq!(
+            RconResponse::try_from(packets.as_slice())?.output,
+            "line 1\nline 2\n"
+        );
+
+        let packets = vec![b"\xff\xff\xff\xffc123".to_vec()];
+        assert_eq!(
+            RconResponse::try_from(packets.as_slice())
+                .unwrap_err()
+                .to_string(),
+            "Invalid header"
+        );
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_rcon() -> Result<()> {
+        // crypt server: the salt follows the 40 character digest
+        let is_crypt = |message: &[u8]| {
+            let salt = message.get(4 + 5 + 40..4 + 5 + 40 + 16).unwrap_or_default();
+            let salt = String::from_utf8_lossy(salt);
+            message == message_hashed("secret", "status", &salt)
+        };
+        let accepts_plain = |message: &[u8]| message.starts_with(b"\xff\xff\xff\xffrcon secret ");
+
+        for accepts in [accepts_plain, is_crypt] {
+            let address = spawn_server(accepts).await?;
+            let options = RconOptions {
+                timeout: Duration::from_millis(200),
+                quiet: Duration::from_millis(50),
+                ..Default::default()
+            };
+            assert_eq!(
+                rcon(&address, "secret", "status", &options).await?,
+                "first\nsecond\n"
+            );
+            assert!(matches!(
+                rcon(&address, "wrong", "status", &options).await,
+                Err(Error::BadRconPassword)
+            ));
+        }
+
+        // no fallback to plain
+        let address = spawn_server(accepts_plain).await?;
+        let options = RconOptions {
+            auth: RconAuth::Crypt,
+            timeout: Duration::from_millis(200),
+            quiet: Duration::from_millis(50),
+            ..Default::default()
+        };
+        assert!(matches!(
+            rcon(&address, "secret", "status", &options).await,
+            Err(Error::BadRconPassword)
+        ));
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    async fn spawn_server(accepts: fn(&[u8]) -> bool) -> Result<String> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 1024];
+            while let Ok((len, from)) = server.recv_from(&mut buf).await {
+                if accepts(&buf[..len]) {
+                    let _ = server.send_to(b"\xff\xff\xff\xffnfirst\n", from).await;
+                    let _ = server.send_to(b"\xff\xff\xff\xffnsecond\n", from).await;
+                } else {
+                    let _ = server
+                        .send_to(b"\xff\xff\xff\xffnBad rcon_password.\n", from)
+                        .await;
+                }
+            }
+        });
+        Ok(address)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::{Error, Result};
+
+/// Reads little endian values and null terminated strings from a byte slice.
+#[derive(Clone, Debug)]
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(Error::UnexpectedEnd)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16> {
+        let b = self.read_bytes(2)?;
+        Ok(i16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        let b = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        let b = self.read_bytes(4)?;
+        Ok(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read a null terminated string, the terminator is optional at the end of data.
+    pub fn read_string(&mut self) -> Result<&'a [u8]> {
+        if self.is_empty() {
+            return Err(Error::UnexpectedEnd);
+        }
+
+        let rest = self.remaining();
+        let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+        let bytes = &rest[..len];
+        self.pos += (len + 1).min(rest.len());
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_read() -> Result<()> {
+        let mut reader = ByteReader::new(b"\x01\xff\xff\xff\xff\xfe\xff\x00\x00\x80\x3ffoo\0bar");
+        assert_eq!(reader.read_u8()?, 1);
+        assert_eq!(reader.read_i32()?, -1);
+        assert_eq!(reader.read_i16()?, -2);
+        assert_eq!(reader.read_f32()?, 1.0);
+        assert_eq!(reader.read_string()?, b"foo");
+        assert_eq!(reader.read_string()?, b"bar");
+        assert!(reader.is_empty());
+        assert!(reader.read_string().is_err());
+        assert!(reader.read_u8().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! Async address resolution with caching.
+use crate::rt;
+use anyhow::{Result, anyhow as e};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// Resolves addresses (host:port) to socket addresses.
+pub trait Resolver: Send + Sync {
+    fn resolve<'a>(&'a self, address: &'a str) -> ResolveFuture<'a>;
+}
+
+/// Resolver using the system resolver, on the blocking thread pool of the runtime.
+#[derive(Clone, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve<'a>(&'a self, address: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move { Ok(rt::lookup_host(address).await?) })
+    }
+}
+
+/// Caches results of another resolver for a fixed time.
+pub struct CachedResolver<R: Resolver> {
+    inner: R,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>,
+}
+
+impl<R: Resolver> CachedResolver<R> {
+    pub fn new(inner: R, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cache: Mutex::default(),
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
+    }
+
+    fn get(&self, address: &str) -> Option<Vec<SocketAddr>> {
+        let cache = self.cache.lock().ok()?;
+        let (resolved_at, addrs) = cache.get(address)?;
+        (resolved_at.elapsed() < self.ttl).then(|| addrs.clone())
+    }
+}
+
+impl<R: Resolver> Resolver for CachedResolver<R> {
+    fn resolve<'a>(&'a self, address: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            if let Some(addrs) = self.get(address) {
+                return Ok(addrs);
+            }
+
+            let addrs = self.inner.resolve(address).await?;
+
+            if let Ok(mut cache) = self.cache.lock() {
+                cache.retain(|_, (resolved_at, _)| resolved_at.elapsed() < self.ttl);
+                cache.insert(address.to_string(), (Instant::now(), addrs.clone()));
+            }
+
+            Ok(addrs)
+        })
+    }
+}
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+static GLOBAL: OnceLock<Arc<dyn Resolver>> = OnceLock::new();
+
+/// Resolver used by all queries, a [`SystemResolver`] cached for [`DEFAULT_TTL`]
+/// unless set using [`set_global`].
+pub fn global() -> Arc<dyn Resolver> {
+    GLOBAL
+        .get_or_init(|| Arc::new(CachedResolver::new(SystemResolver, DEFAULT_TTL)))
+        .clone()
+}
+
+/// Set the resolver used by all queries, fails if already set or used.
+pub fn set_global(resolver: Arc<dyn Resolver>) -> Result<()> {
+    GLOBAL
+        .set(resolver)
+        .map_err(|_| e!("Global resolver is already set"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingResolver {
+        count: AtomicUsize,
+    }
+
+    impl Resolver for CountingResolver {
+        fn resolve<'a>(&'a self, address: &'a str) -> ResolveFuture<'a> {
+            Box::pin(async move {
+                self.count.fetch_add(1, Ordering::SeqCst);
+                match address {
+                    "quake.se:28501" => Ok(vec!["10.0.0.1:28501".parse()?]),
+                    _ => Err(e!("Unable to resolve address {}", address)),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_system_resolver() -> Result<()> {
+        assert_eq!(
+            SystemResolver.resolve("127.0.0.1:27500").await?,
+            vec!["127.0.0.1:27500".parse()?]
+        );
+        assert!(SystemResolver.resolve("foo").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cached_resolver() -> Result<()> {
+        let resolver = CachedResolver::new(CountingResolver::default(), Duration::from_millis(50));
+        let expected: Vec<SocketAddr> = vec!["10.0.0.1:28501".parse()?];
+
+        assert_eq!(resolver.resolve("quake.se:28501").await?, expected);
+        assert_eq!(resolver.resolve("quake.se:28501").await?, expected);
+        assert_eq!(resolver.inner.count.load(Ordering::SeqCst), 1);
+
+        // errors are not cached
+        assert!(resolver.resolve("foo:1").await.is_err());
+        assert!(resolver.resolve("foo:1").await.is_err());
+        assert_eq!(resolver.inner.count.load(Ordering::SeqCst), 3);
+
+        // expired
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(resolver.resolve("quake.se:28501").await?, expected);
+        assert_eq!(resolver.inner.count.load(Ordering::SeqCst), 4);
+
+        resolver.clear();
+        assert_eq!(resolver.resolve("quake.se:28501").await?, expected);
+        assert_eq!(resolver.inner.count.load(Ordering::SeqCst), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {crate::rt, anyhow::Result};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// Max number of attempts, including the first one.
+    pub attempts: u32,
+    /// Timeout for each attempt.
+    pub timeout: Duration,
+    /// Delay before the second attempt.
+    pub backoff: Duration,
+    /// Factor the delay is multiplied with after each failed attempt.
+    pub backoff_factor: u32,
+    /// Upper bound of the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            timeout: Duration::from_millis(500),
+            backoff: Duration::from_millis(100),
+            backoff_factor: 2,
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Retried<T> {
+    pub value: T,
+    /// Number of attempts made, including the successful one.
+    pub attempts: u32,
+}
+
+impl RetryPolicy {
+    /// Single attempt, no retries.
+    pub fn once(timeout: Duration) -> Self {
+        Self {
+            attempts: 1,
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the given attempt (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+
+        let factor = self.backoff_factor.saturating_pow(attempt - 2);
+        self.backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Run `f` until it succeeds or all attempts are used up.
+    ///
+    /// `f` is given the per-attempt timeout. The error of the last attempt is
+    /// returned if all attempts fail.
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn run<T, E, F, Fut>(&self, mut f: F) -> Result<Retried<T>, E>
+    where
+        F: FnMut(Duration) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let attempts = self.attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            match f(self.timeout).await {
+                Ok(value) => {
+                    return Ok(Retried {
+                        value,
+                        attempts: attempt,
+                    });
+                }
+                Err(err) if attempt >= attempts => return Err(err),
+                Err(_) => {
+                    attempt += 1;
+                    rt::sleep(self.delay(attempt)).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    use anyhow::anyhow as e;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_delay() {
+        let ms = Duration::from_millis;
+        let policy = RetryPolicy {
+            attempts: 5,
+            backoff: ms(100),
+            backoff_factor: 2,
+            max_backoff: ms(300),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), ms(0));
+        assert_eq!(policy.delay(2), ms(100));
+        assert_eq!(policy.delay(3), ms(200));
+        assert_eq!(policy.delay(4), ms(300));
+        assert_eq!(policy.delay(40), ms(300));
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_run() -> Result<()> {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        // succeeds on third attempt
+        {
+            let mut calls = 0;
+            let res = policy
+                .run(|_| {
+                    calls += 1;
+                    let calls = calls;
+                    async move {
+                        match calls {
+                            3 => Ok("ok"),
+                            _ => Err(e!("fail {}", calls)),
+                        }
+                    }
+                })
+                .await?;
+            assert_eq!(
+                res,
+                Retried {
+                    value: "ok",
+                    attempts: 3
+                }
+            );
+        }
+
+        // all attempts fail
+        {
+            let mut calls = 0;
+            let res: Result<Retried<()>> = policy
+                .run(|_| {
+                    calls += 1;
+                    let calls = calls;
+                    async move { Err(e!("fail {}", calls)) }
+                })
+                .await;
+            assert_eq!(res.unwrap_err().to_string(), "fail 3");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Async runtime support, tokio or async-io (smol, async-std).
+//!
+//! async-io is used if both are enabled (e.g. `smol` and `http`), it does not
+//! depend on the executor and also works within a tokio runtime.
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+#[cfg(all(feature = "tokio", not(feature = "async-io")))]
+pub(crate) use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+};
+
+#[cfg(feature = "async-io")]
+pub(crate) use {
+    async_net::{TcpStream, UdpSocket},
+    futures_lite::io::{AsyncReadExt, AsyncWriteExt},
+};
+
+/// Error of [`timeout`].
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+/// Run a future, failing if it does not complete within the given duration.
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    {
+        tokio::time::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed)
+    }
+
+    #[cfg(feature = "async-io")]
+    {
+        futures_lite::future::or(async { Ok(future.await) }, async {
+            async_io::Timer::after(duration).await;
+            Err(Elapsed)
+        })
+        .await
+    }
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(feature = "async-io")]
+    async_io::Timer::after(duration).await;
+}
+
+/// Resolve an address (host:port) using the system resolver, on a thread pool.
+pub(crate) async fn lookup_host(address: &str) -> io::Result<Vec<SocketAddr>> {
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    {
+        Ok(tokio::net::lookup_host(address).await?.collect())
+    }
+
+    #[cfg(feature = "async-io")]
+    {
+        async_net::resolve(address).await
+    }
+}
+
+/// Run a future in the background.
+///
+/// async-io has no executor of its own, the future is run on a new thread.
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    tokio::spawn(future);
+
+    #[cfg(feature = "async-io")]
+    std::thread::spawn(move || async_io::block_on(future));
+}
+
+/// Channel for sending a single value.
+pub(crate) fn oneshot<T>() -> (OneshotSender<T>, OneshotReceiver<T>) {
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    #[cfg(feature = "async-io")]
+    let (tx, rx) = async_channel::bounded(1);
+
+    (OneshotSender(tx), OneshotReceiver(rx))
+}
+
+pub(crate) struct OneshotSender<T>(
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))] tokio::sync::oneshot::Sender<T>,
+    #[cfg(feature = "async-io")] async_channel::Sender<T>,
+);
+
+impl<T> OneshotSender<T> {
+    /// Send a value, it is dropped if the receiver is gone.
+    pub(crate) fn send(self, value: T) {
+        #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+        let _ = self.0.send(value);
+
+        #[cfg(feature = "async-io")]
+        let _ = self.0.try_send(value);
+    }
+}
+
+pub(crate) struct OneshotReceiver<T>(
+    #[cfg(all(feature = "tokio", not(feature = "async-io")))] tokio::sync::oneshot::Receiver<T>,
+    #[cfg(feature = "async-io")] async_channel::Receiver<T>,
+);
+
+impl<T> OneshotReceiver<T> {
+    /// Wait for the value, `None` if the sender was dropped without sending.
+    pub(crate) async fn recv(self) -> Option<T> {
+        #[cfg(all(feature = "tokio", not(feature = "async-io")))]
+        {
+            self.0.await.ok()
+        }
+
+        #[cfg(feature = "async-io")]
+        {
+            self.0.recv().await.ok()
+        }
+    }
+}
+
+/// Run futures concurrently on the current task, outputs are in the same order.
+pub(crate) async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut futures: Vec<Pin<Box<F>>> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+
+    std::future::poll_fn(|cx| {
+        let mut is_pending = false;
+
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_some() {
+                continue;
+            }
+            match future.as_mut().poll(cx) {
+                Poll::Ready(value) => *output = Some(value),
+                Poll::Pending => is_pending = true,
+            }
+        }
+
+        match is_pending {
+            true => Poll::Pending,
+            false => Poll::Ready(()),
+        }
+    })
+    .await;
+
+    outputs.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        assert_eq!(
+            timeout(Duration::from_millis(50), async { 1 }).await.ok(),
+            Some(1)
+        );
+        assert!(
+            timeout(Duration::from_millis(10), sleep(Duration::from_secs(1)))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oneshot() {
+        let (tx, rx) = oneshot();
+        spawn(async move { tx.send(1) });
+        assert_eq!(rx.recv().await, Some(1));
+
+        let (tx, rx) = oneshot::<u8>();
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_join_all() {
+        let delays = [30, 10, 20];
+        let outputs = join_all(delays.iter().map(|&ms| async move {
+            sleep(Duration::from_millis(ms)).await;
+            ms
+        }))
+        .await;
+        assert_eq!(outputs, vec![30, 10, 20]);
+    }
+}
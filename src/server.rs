@@ -1,15 +1,28 @@
-use anyhow::Result;
 use std::time::Duration;
 
 pub use quake_serverinfo::Settings;
 
 use crate::client::QuakeClient;
 use crate::hostport::Hostport;
+use crate::protocol_extensions::ProtocolExtensions;
 use crate::qtv::QtvStream;
 use crate::server_type::ServerType;
 use crate::software_type::SoftwareType;
-use crate::svc_status;
-use crate::{net_extra, svc_qtvusers};
+
+#[cfg(any(feature = "tokio", feature = "async-io", feature = "blocking"))]
+use {
+    crate::error::Error, crate::svc_qtvusers::QtvusersResponse,
+    crate::svc_status::Status119Response, anyhow::Result, std::net::SocketAddr,
+};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::protocol::{QtvusersQuery, StatusQuery},
+    crate::retry::{Retried, RetryPolicy},
+    crate::transport::{self, Transport, UdpTransport},
+    crate::{net_extra, nq, svc_challenge},
+    std::time::Instant,
+};
 
 #[cfg(feature = "json")]
 use {
@@ -28,18 +41,106 @@ pub struct QuakeServer {
     pub settings: Settings,
     pub clients: Vec<QuakeClient>,
     pub qtv_stream: Option<QtvStream>,
+    /// Round-trip time of the status request.
+    pub latency: Option<Duration>,
+    /// Set by `QuakeServer::probe_protocol_extensions`.
+    pub protocol_extensions: Option<ProtocolExtensions>,
 }
 
 impl QuakeServer {
-    pub async fn try_from_address(address: &str, timeout: Duration) -> Result<Self> {
-        let mut res = svc_status::status_119(address, timeout).await?;
-        let ip = net_extra::address_to_ip(address).unwrap_or_default();
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn try_from_address(address: &str, timeout: Duration) -> Result<Self, Error> {
+        let res = Self::try_from_address_with_retry(address, &RetryPolicy::once(timeout)).await?;
+        Ok(res.value)
+    }
+
+    /// Query a server, retrying lost requests.
+    ///
+    /// `attempts` is the number of attempts of the status request.
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn try_from_address_with_retry(
+        address: &str,
+        policy: &RetryPolicy,
+    ) -> Result<Retried<Self>, Error> {
+        Self::try_from_address_with_transport(&UdpTransport, address, policy).await
+    }
+
+    /// Query a server using the given transport, e.g. a [`crate::engine::QueryEngine`].
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn try_from_address_with_transport(
+        transport: &dyn Transport,
+        address: &str,
+        policy: &RetryPolicy,
+    ) -> Result<Retried<Self>, Error> {
+        let resolved = net_extra::resolve(address).await?;
+        let socket_address = resolved.to_string();
+        let socket_address = socket_address.as_str();
+        let Retried {
+            value: (res, latency),
+            attempts,
+        } = policy
+            .run(|timeout| async move {
+                let start = Instant::now();
+                let res =
+                    transport::query(transport, socket_address, &StatusQuery::default(), timeout)
+                        .await?;
+                Ok::<_, Error>((res.value, start.elapsed()))
+            })
+            .await?;
+        let qtvusers = match res.qtv_stream {
+            Some(_) => policy
+                .run(|timeout| transport::query(transport, socket_address, &QtvusersQuery, timeout))
+                .await
+                .ok()
+                .map(|r| r.value),
+            None => None,
+        };
+        let value = Self::try_from_responses(address, resolved, res, qtvusers, Some(latency))?;
+        Ok(Retried { value, attempts })
+    }
+
+    /// Detect protocol extensions using a challenge request.
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn probe_protocol_extensions(&mut self, timeout: Duration) -> Result<()> {
+        let res = svc_challenge::getchallenge(&self.address.to_string(), timeout).await?;
+        self.protocol_extensions = Some(ProtocolExtensions {
+            z_ext: self.settings.z_ext.map(|z| z as u32),
+            ..res.extensions
+        });
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    pub async fn try_from_nq_address(address: &str, timeout: Duration) -> Result<Self> {
+        let resolved = net_extra::resolve(address).await?;
+        let res = nq::query(&resolved.to_string(), timeout).await?;
+
+        Ok(QuakeServer {
+            server_type: ServerType::NqServer,
+            software_type: SoftwareType::from_nq_rules(&res.rules),
+            address: Hostport::try_from(address)?,
+            ip: resolved.ip().to_string(),
+            settings: res.settings,
+            clients: res.clients,
+            qtv_stream: None,
+            latency: None,
+            protocol_extensions: None,
+        })
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io", feature = "blocking"))]
+    pub(crate) fn try_from_responses(
+        address: &str,
+        resolved: SocketAddr,
+        mut res: Status119Response,
+        qtvusers: Option<QtvusersResponse>,
+        latency: Option<Duration>,
+    ) -> Result<Self, Error> {
+        let ip = resolved.ip().to_string();
 
         res.qtv_stream = match res.qtv_stream {
             Some(qtv_stream) => {
-                let res = svc_qtvusers::qtvusers(address, timeout)
-                    .await
-                    .unwrap_or_default();
+                let res = qtvusers.unwrap_or_default();
                 Some(QtvStream {
                     client_names: res.client_names,
                     ..qtv_stream
@@ -50,7 +151,8 @@ impl QuakeServer {
 
         let address = {
             let address_str = res.settings.clone().hostport.unwrap_or(address.to_string());
-            Hostport::try_from(address_str.as_str())?
+            Hostport::try_from(address_str.as_str())
+                .map_err(|_| Error::parse("hostport", &address_str))?
         };
         let version = res.settings.version.as_deref().unwrap_or("");
 
@@ -62,6 +164,8 @@ impl QuakeServer {
             settings: res.settings,
             clients: res.clients,
             qtv_stream: res.qtv_stream,
+            latency,
+            protocol_extensions: None,
         })
     }
 }
@@ -72,9 +176,9 @@ impl Serialize for QuakeServer {
     where
         S: Serializer,
     {
-        let field_count: usize = 6 + match self.software_type {
+        let field_count: usize = 7 + match self.software_type {
             SoftwareType::Qtv | SoftwareType::Qwfwd => 2,
-            _ => 5,
+            _ => 6,
         };
 
         let mut state = serializer.serialize_struct("QuakeServer", field_count)?;
@@ -84,6 +188,7 @@ impl Serialize for QuakeServer {
         state.serialize_field("ip", &self.ip)?;
         state.serialize_field("port", &self.address.port)?;
         state.serialize_field("address", &self.address)?;
+        state.serialize_field("latency", &self.latency.map(|l| l.as_millis() as u64))?;
 
         if self.software_type == SoftwareType::Qtv {
             let qtv = QtvServer::from(self);
@@ -100,27 +205,43 @@ impl Serialize for QuakeServer {
             state.serialize_field("players", &server.players)?;
             state.serialize_field("spectators", &server.spectators)?;
             state.serialize_field("qtv_stream", &server.qtv_stream)?;
+            state.serialize_field("protocol_extensions", &self.protocol_extensions)?;
         }
 
         state.end()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, any(feature = "tokio", feature = "async-io")))]
 mod tests {
     use super::*;
+    use crate::mock::MockServer;
     use anyhow::Result;
     use pretty_assertions::assert_eq;
 
     #[tokio::test]
     async fn test_try_from_address() -> Result<()> {
-        assert!(
-            QuakeServer::try_from_address("foo.bar:666", Duration::from_millis(50))
-                .await
-                .is_err()
-        );
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let res = QuakeServer::try_from_address(
+            &silent.local_addr()?.to_string(),
+            Duration::from_millis(50),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Timeout)));
+
+        let mut mock = MockServer::mvdsv("QUAKE.SE KTX:28501");
+        mock.settings.hostport = Some("quake.se:28501".to_string());
+        mock.qtv_stream = Some(QtvStream {
+            id: 1,
+            name: "QUAKE.SE KTX Qtv (1)".to_string(),
+            number: 1,
+            address: Hostport::new("quake.se".to_string(), 28000),
+            client_count: 1,
+            client_names: vec!["bar".to_string()],
+        });
+        let mock = mock.spawn()?;
         let server =
-            QuakeServer::try_from_address("quake.se:28501", Duration::from_secs_f32(0.5)).await?;
+            QuakeServer::try_from_address(&mock.address(), Duration::from_secs_f32(0.5)).await?;
 
         assert!(
             server
@@ -137,6 +258,121 @@ mod tests {
                 port: 28501,
             }
         );
+        assert_eq!(server.ip, "127.0.0.1");
+        assert_eq!(
+            server.qtv_stream.map(|s| s.client_names),
+            Some(vec!["bar".to_string()])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try_from_address_with_transport() -> Result<()> {
+        let transport = transport::MemoryTransport::new();
+        transport.insert(
+            "10.0.0.1:28501",
+            b"\xff\xff\xff\xffstatus 119",
+            b"\xff\xff\xff\xffn\\hostname\\foo\\*version\\MVDSV 0.36\n63 43 41 25 \"XantoM\" \"\" 4 4 \"red\" \"\"\nqtv 1 \"foo Qtv (1)\" \"1@10.0.0.1:28000\" 1\n",
+        );
+        transport.insert(
+            "10.0.0.1:28501",
+            b"\xff\xff\xff\xffqtvusers",
+            b"\xff\xff\xff\xffnqtvusers 1 \"bar\"\n",
+        );
+
+        let res = QuakeServer::try_from_address_with_transport(
+            &transport,
+            "10.0.0.1:28501",
+            &RetryPolicy::once(Duration::from_millis(50)),
+        )
+        .await?;
+        assert_eq!(res.attempts, 1);
+        let server = res.value;
+        assert_eq!(server.server_type, ServerType::GameServer);
+        assert_eq!(server.ip, "10.0.0.1");
+        assert_eq!(server.settings.hostname, Some("foo".to_string()));
+        assert_eq!(server.clients.len(), 1);
+        assert_eq!(
+            server.qtv_stream.map(|s| s.client_names),
+            Some(vec!["bar".to_string()])
+        );
+
+        let res = QuakeServer::try_from_address_with_transport(
+            &transport,
+            "10.0.0.2:28501",
+            &RetryPolicy::once(Duration::from_millis(50)),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Timeout)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try_from_address_with_retry() -> Result<()> {
+        // first status request is lost
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let _ = server.recv_from(&mut buf).await;
+            if let Ok((_, from)) = server.recv_from(&mut buf).await {
+                let _ = server
+                    .send_to(b"\xff\xff\xff\xffn\\hostname\\foo\n", from)
+                    .await;
+            }
+        });
+
+        let policy = RetryPolicy {
+            attempts: 3,
+            timeout: Duration::from_millis(50),
+            backoff: Duration::ZERO,
+            ..Default::default()
+        };
+        let res = QuakeServer::try_from_address_with_retry(&address, &policy).await?;
+        assert_eq!(res.attempts, 2);
+        assert_eq!(res.value.settings.hostname, Some("foo".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_probe_protocol_extensions() -> Result<()> {
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let address = Hostport::try_from(server.local_addr()?.to_string().as_str())?;
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            if let Ok((_, from)) = server.recv_from(&mut buf).await {
+                let _ = server
+                    .send_to(b"\xff\xff\xff\xffc123\0MVD1\x01\x00\x00\x00", from)
+                    .await;
+            }
+        });
+
+        let mut server = QuakeServer {
+            server_type: ServerType::GameServer,
+            software_type: SoftwareType::Mvdsv,
+            address,
+            ip: "127.0.0.1".to_string(),
+            settings: Settings {
+                z_ext: Some(511),
+                ..Default::default()
+            },
+            clients: vec![],
+            qtv_stream: None,
+            latency: None,
+            protocol_extensions: None,
+        };
+        server
+            .probe_protocol_extensions(Duration::from_millis(200))
+            .await?;
+
+        assert_eq!(
+            server.protocol_extensions,
+            Some(ProtocolExtensions {
+                mvd: Some(1),
+                z_ext: Some(511),
+                ..Default::default()
+            })
+        );
         Ok(())
     }
 }
@@ -13,6 +13,7 @@ pub enum ServerType {
     GameServer,
     ProxyServer,
     QtvServer,
+    NqServer,
     Unknown,
 }
 
@@ -22,6 +23,7 @@ impl Display for ServerType {
             ServerType::GameServer => write!(f, "GameServer"),
             ServerType::ProxyServer => write!(f, "ProxyServer"),
             ServerType::QtvServer => write!(f, "QtvServer"),
+            ServerType::NqServer => write!(f, "NqServer"),
             ServerType::Unknown => write!(f, "Unknown"),
         }
     }
@@ -56,6 +58,7 @@ mod tests {
         assert_eq!(ServerType::GameServer.to_string(), "GameServer");
         assert_eq!(ServerType::ProxyServer.to_string(), "ProxyServer");
         assert_eq!(ServerType::QtvServer.to_string(), "QtvServer");
+        assert_eq!(ServerType::NqServer.to_string(), "NqServer");
         assert_eq!(ServerType::Unknown.to_string(), "Unknown");
     }
 
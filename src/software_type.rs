@@ -13,6 +13,8 @@ pub enum SoftwareType {
     FortressOne,
     Fte,
     Mvdsv,
+    NetQuake,
+    ProQuake,
     Qtv,
     Qwfwd,
     Unknown,
@@ -24,6 +26,8 @@ impl Display for SoftwareType {
             SoftwareType::FortressOne => write!(f, "FortressOne"),
             SoftwareType::Fte => write!(f, "FTE"),
             SoftwareType::Mvdsv => write!(f, "MVDSV"),
+            SoftwareType::NetQuake => write!(f, "NetQuake"),
+            SoftwareType::ProQuake => write!(f, "ProQuake"),
             SoftwareType::Qtv => write!(f, "QTV"),
             SoftwareType::Qwfwd => write!(f, "QWFWD"),
             SoftwareType::Unknown => write!(f, "Unknown"),
@@ -45,6 +49,14 @@ impl SoftwareType {
             _ => SoftwareType::Unknown,
         }
     }
+
+    /// NQ servers do not report a version, ProQuake is detected by its `pq_` cvars.
+    pub fn from_nq_rules(rules: &[(String, String)]) -> Self {
+        match rules.iter().any(|(name, _)| name.starts_with("pq_")) {
+            true => SoftwareType::ProQuake,
+            false => SoftwareType::NetQuake,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -56,6 +68,8 @@ mod tests {
         assert_eq!(SoftwareType::FortressOne.to_string(), "FortressOne");
         assert_eq!(SoftwareType::Fte.to_string(), "FTE");
         assert_eq!(SoftwareType::Mvdsv.to_string(), "MVDSV");
+        assert_eq!(SoftwareType::NetQuake.to_string(), "NetQuake");
+        assert_eq!(SoftwareType::ProQuake.to_string(), "ProQuake");
         assert_eq!(SoftwareType::Qtv.to_string(), "QTV");
         assert_eq!(SoftwareType::Qwfwd.to_string(), "QWFWD");
         assert_eq!(SoftwareType::Unknown.to_string(), "Unknown");
@@ -77,4 +91,18 @@ mod tests {
             SoftwareType::Unknown
         );
     }
+
+    #[test]
+    fn test_from_nq_rules() {
+        let rule = |name: &str| (name.to_string(), "1".to_string());
+        assert_eq!(SoftwareType::from_nq_rules(&[]), SoftwareType::NetQuake);
+        assert_eq!(
+            SoftwareType::from_nq_rules(&[rule("deathmatch")]),
+            SoftwareType::NetQuake
+        );
+        assert_eq!(
+            SoftwareType::from_nq_rules(&[rule("deathmatch"), rule("pq_fullpitch")]),
+            SoftwareType::ProQuake
+        );
+    }
 }
@@ -0,0 +1,130 @@
+use std::fmt::Display;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Flags of the `status` request.
+///
+/// see: https://github.com/QW-Group/mvdsv/blob/master/src/sv_main.c#L603-L610
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StatusFlags(u8);
+
+impl StatusFlags {
+    /// `status` without argument: serverinfo and players (no spectators), without teams.
+    ///
+    /// Not a flag but the absence of flags, check for it with
+    /// [`StatusFlags::is_oldstyle`] (`contains(OLDSTYLE)` is always true).
+    pub const OLDSTYLE: Self = Self(0);
+    pub const SERVERINFO: Self = Self(1);
+    pub const PLAYERS: Self = Self(2);
+    pub const SPECTATORS: Self = Self(4);
+    /// For ASE: spectators are shown with frags `S` and positive ping.
+    pub const SPECTATORS_AS_PLAYERS: Self = Self(8);
+    pub const SHOWTEAMS: Self = Self(16);
+    pub const SHOWQTV: Self = Self(32);
+    pub const SHOWFLAGS: Self = Self(64);
+    /// All flags except `SPECTATORS_AS_PLAYERS`, i.e. `status 119`.
+    pub const FULL: Self = Self(119);
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// True if all flags of `other` are set, always true for [`StatusFlags::OLDSTYLE`].
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_oldstyle(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn has_serverinfo(&self) -> bool {
+        self.is_oldstyle() || self.contains(Self::SERVERINFO)
+    }
+
+    pub const fn has_clients(&self) -> bool {
+        self.is_oldstyle() || self.contains(Self::PLAYERS) || self.contains(Self::SPECTATORS)
+    }
+
+    /// Request message for these flags.
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = b"\xff\xff\xff\xffstatus".to_vec();
+        if !self.is_oldstyle() {
+            message.extend_from_slice(format!(" {}", self.0).as_bytes());
+        }
+        message
+    }
+}
+
+impl Display for StatusFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl BitOr for StatusFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for StatusFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for StatusFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_bits() {
+        let flags = StatusFlags::SERVERINFO
+            | StatusFlags::PLAYERS
+            | StatusFlags::SPECTATORS
+            | StatusFlags::SHOWTEAMS
+            | StatusFlags::SHOWQTV
+            | StatusFlags::SHOWFLAGS;
+        assert_eq!(flags, StatusFlags::FULL);
+        assert_eq!(flags.bits(), 119);
+        assert!(flags.contains(StatusFlags::SHOWQTV));
+        assert!(!flags.contains(StatusFlags::SPECTATORS_AS_PLAYERS));
+        assert_eq!(flags & StatusFlags::PLAYERS, StatusFlags::PLAYERS);
+        assert_eq!(StatusFlags::default(), StatusFlags::OLDSTYLE);
+    }
+
+    #[test]
+    fn test_has() {
+        assert!(StatusFlags::OLDSTYLE.has_serverinfo());
+        assert!(StatusFlags::OLDSTYLE.has_clients());
+        assert!(StatusFlags::SERVERINFO.has_serverinfo());
+        assert!(!StatusFlags::SERVERINFO.has_clients());
+        assert!(!StatusFlags::SPECTATORS.has_serverinfo());
+        assert!(StatusFlags::SPECTATORS.has_clients());
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(StatusFlags::OLDSTYLE.message(), b"\xff\xff\xff\xffstatus");
+        assert_eq!(
+            StatusFlags::SERVERINFO.message(),
+            b"\xff\xff\xff\xffstatus 1"
+        );
+        assert_eq!(StatusFlags::FULL.message(), b"\xff\xff\xff\xffstatus 119");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::{Error, Result, parse_field};
+use crate::protocol_extensions::ProtocolExtensions;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::protocol::ChallengeQuery,
+    crate::transport::{self, UdpTransport},
+    std::time::Duration,
+};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn getchallenge(address: &str, timeout: Duration) -> Result<ChallengeResponse> {
+    transport::query(&UdpTransport, address, &ChallengeQuery, timeout).await
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ChallengeResponse {
+    pub challenge: i32,
+    pub extensions: ProtocolExtensions,
+}
+
+impl TryFrom<&[u8]> for ChallengeResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        // validate header
+        let header = b"\xff\xff\xff\xffc".to_vec();
+
+        if !bytes.starts_with(&header) {
+            return Err(Error::InvalidHeader);
+        }
+
+        // challenge number (a null terminated string), followed by (protocol, value)
+        // pairs of little endian u32
+        let body = &bytes[header.len()..];
+        let number_len = body
+            .iter()
+            .enumerate()
+            .take_while(|(i, b)| b.is_ascii_digit() || (*i == 0 && **b == b'-'))
+            .count();
+        let number = String::from_utf8_lossy(&body[..number_len]);
+        let challenge: i32 = parse_field(&number, "challenge", &number)?;
+
+        let rest = &body[number_len..];
+        let rest = rest.strip_prefix(b"\0").unwrap_or(rest);
+        let mut extensions = ProtocolExtensions::default();
+
+        for pair in rest.chunks_exact(8) {
+            let protocol = u32::from_le_bytes([pair[0], pair[1], pair[2], pair[3]]);
+            let value = u32::from_le_bytes([pair[4], pair[5], pair[6], pair[7]]);
+            extensions.set(protocol, value);
+        }
+
+        Ok(Self {
+            challenge,
+            extensions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol_extensions::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_try_from() -> Result<()> {
+        // invalid
+        {
+            let res = ChallengeResponse::try_from([0].as_slice());
+            assert_eq!(res.unwrap_err().to_string(), "Invalid header".to_string());
+        }
+        {
+            let res = ChallengeResponse::try_from(b"\xff\xff\xff\xffcFTEX".as_slice());
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                "Invalid challenge".to_string()
+            );
+        }
+
+        // without extensions
+        {
+            let res = ChallengeResponse::try_from(b"\xff\xff\xff\xffc-12345".as_slice())?;
+            assert_eq!(
+                res,
+                ChallengeResponse {
+                    challenge: -12345,
+                    extensions: ProtocolExtensions::default(),
+                }
+            );
+            let res = ChallengeResponse::try_from(b"\xff\xff\xff\xffc-12345\0".as_slice())?;
+            assert_eq!(res.challenge, -12345);
+            assert_eq!(res.extensions, ProtocolExtensions::default());
+        }
+
+        // with extensions
+        {
+            let mut bytes = b"\xff\xff\xff\xffc98765\0".to_vec();
+            bytes.extend(b"FTEX");
+            bytes.extend((FTE_PEXT_CHUNKEDDOWNLOADS | FTE_PEXT_TRANS).to_le_bytes());
+            bytes.extend(b"FTE2");
+            bytes.extend(FTE_PEXT2_VOICECHAT.to_le_bytes());
+            bytes.extend(b"MVD1");
+            bytes.extend(MVD_PEXT1_FLOATCOORDS.to_le_bytes());
+            bytes.extend(b"FRAG");
+            bytes.extend(1450_u32.to_le_bytes());
+
+            let res = ChallengeResponse::try_from(bytes.as_slice())?;
+            assert_eq!(res.challenge, 98765);
+            assert_eq!(
+                res.extensions,
+                ProtocolExtensions {
+                    fte: Some(FTE_PEXT_CHUNKEDDOWNLOADS | FTE_PEXT_TRANS),
+                    fte2: Some(FTE_PEXT2_VOICECHAT),
+                    mvd: Some(MVD_PEXT1_FLOATCOORDS),
+                    z_ext: None,
+                    fragment_mtu: Some(1450),
+                }
+            );
+            assert!(res.extensions.supports_chunked_downloads());
+        }
+
+        // single extension
+        {
+            let mut bytes = b"\xff\xff\xff\xffc123\0FTEX".to_vec();
+            bytes.extend(0x20000000_u32.to_le_bytes());
+
+            let res = ChallengeResponse::try_from(bytes.as_slice())?;
+            assert_eq!(res.challenge, 123);
+            assert_eq!(res.extensions.fte, Some(0x20000000));
+        }
+
+        Ok(())
+    }
+}
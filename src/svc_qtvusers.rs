@@ -1,21 +1,27 @@
-use crate::tokenize::tokenize;
-use anyhow::{Result, anyhow as e};
+use crate::error::{Error, Result, parse_field};
+use crate::tokenize::tokens;
 use quake_text::bytestr;
-use std::time::Duration;
-use tinyudp;
+use std::borrow::Cow;
 
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::protocol::QtvusersQuery,
+    crate::retry::{Retried, RetryPolicy},
+    crate::transport::{self, UdpTransport},
+    std::time::Duration,
+};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
 pub async fn qtvusers(address: &str, timeout: Duration) -> Result<QtvusersResponse> {
-    // https://github.com/QW-Group/mvdsv/blob/master/src/sv_demo_qtv.c#L1379
-    let bytes = {
-        let message = b"\xff\xff\xff\xffqtvusers".to_vec();
-        let options = tinyudp::ReadOptions {
-            timeout,
-            buffer_size: 4 * 1024, // 4 kb
-        };
-        tinyudp::send_and_receive(address, &message, options).await?
-    };
+    transport::query(&UdpTransport, address, &QtvusersQuery, timeout).await
+}
 
-    QtvusersResponse::try_from(bytes.as_slice())
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn qtvusers_with_retry(
+    address: &str,
+    policy: &RetryPolicy,
+) -> Result<Retried<QtvusersResponse>> {
+    policy.run(|timeout| qtvusers(address, timeout)).await
 }
 
 #[derive(Debug, Default, Eq, PartialEq)]
@@ -25,14 +31,14 @@ pub struct QtvusersResponse {
 }
 
 impl TryFrom<&[u8]> for QtvusersResponse {
-    type Error = anyhow::Error;
+    type Error = Error;
 
     fn try_from(bytes: &[u8]) -> Result<Self> {
         // validate header
         let header = b"\xff\xff\xff\xffnqtvusers ".to_vec();
 
         if !bytes.starts_with(&header) {
-            return Err(e!("Invalid response header"));
+            return Err(Error::InvalidHeader);
         }
 
         // extract body
@@ -40,14 +46,18 @@ impl TryFrom<&[u8]> for QtvusersResponse {
             let end_pos = bytes
                 .iter()
                 .position(|&b| b == b'\n')
-                .ok_or(e!("Invalid response body"))?;
+                .ok_or(Error::InvalidBody)?;
             &bytes[header.len()..end_pos]
         };
 
         // parse body
-        let parts = tokenize(&bytestr::to_unicode(body));
-        let stream_id = parts[0].parse::<usize>()?;
-        let client_names = parts[1..].iter().map(|s| s.to_string()).collect();
+        let text = bytestr::to_unicode(body);
+        let parts: Vec<Cow<str>> = tokens(&text).collect();
+        let (stream_id, names) = parts
+            .split_first()
+            .ok_or_else(|| Error::parse("stream id", &text))?;
+        let stream_id = parse_field(stream_id, "stream id", &text)?;
+        let client_names = names.iter().map(|s| s.to_string()).collect();
 
         Ok(Self {
             stream_id,
@@ -84,6 +94,13 @@ mod tests {
                 }
             );
         }
+        {
+            let bytes = b"\xff\xff\xff\xffnqtvusers \n".as_slice();
+            assert_eq!(
+                QtvusersResponse::try_from(bytes).unwrap_err().to_string(),
+                "Invalid stream id"
+            );
+        }
 
         Ok(())
     }
@@ -1,91 +1,163 @@
 use crate::client::QuakeClient;
+use crate::error::{Error, Result};
 use crate::qtv::QtvStream;
-use anyhow::{Result, anyhow as e};
+use crate::status_flags::StatusFlags;
 use quake_serverinfo::Settings;
 use std::io::{BufRead, Cursor};
-use std::time::Duration;
-use tinyudp;
 
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use {
+    crate::protocol::StatusQuery,
+    crate::retry::{Retried, RetryPolicy},
+    crate::transport::{self, UdpTransport},
+    std::time::Duration,
+};
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn status(
+    address: &str,
+    flags: StatusFlags,
+    timeout: Duration,
+) -> Result<StatusResponse> {
+    let parsed = status_lenient(address, flags, timeout).await?;
+    Ok(parsed.value)
+}
+
+/// Like [`status`] but reports rows that can not be parsed, see [`StatusResponse::parse_lenient`].
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn status_lenient(
+    address: &str,
+    flags: StatusFlags,
+    timeout: Duration,
+) -> Result<Parsed<StatusResponse>> {
+    transport::query(&UdpTransport, address, &StatusQuery::new(flags), timeout).await
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
 pub async fn status_119(address: &str, timeout: Duration) -> Result<Status119Response> {
-    // see: https://github.com/QW-Group/mvdsv/blob/master/src/sv_main.c#L603-L610
-    // #define STATUS_OLDSTYLE                 0
-    // #define STATUS_SERVERINFO               1
-    // #define STATUS_PLAYERS                  2
-    // #define STATUS_SPECTATORS               4
-    // #define STATUS_SPECTATORS_AS_PLAYERS    8 //for ASE - change only frags: show as "S"
-    // #define STATUS_SHOWTEAMS                16
-    // #define STATUS_SHOWQTV                  32
-    // #define STATUS_SHOWFLAGS                64
     // svc_status 119 = all except for STATUS_SPECTATORS_AS_PLAYERS
-    let response_bytes = {
-        let message = b"\xff\xff\xff\xffstatus 119".to_vec();
-        let options = tinyudp::ReadOptions {
-            timeout,
-            buffer_size: 64 * 1024, // 64 kb
-        };
-        tinyudp::send_and_receive(address, &message, options).await?
-    };
-    let response = Status119Response::try_from(response_bytes.as_slice())?;
-    Ok(response)
+    status(address, StatusFlags::FULL, timeout).await
+}
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+pub async fn status_119_with_retry(
+    address: &str,
+    policy: &RetryPolicy,
+) -> Result<Retried<Status119Response>> {
+    policy.run(|timeout| status_119(address, timeout)).await
 }
 
 #[derive(Debug)]
-pub struct Status119Response {
+pub struct StatusResponse {
     pub settings: Settings,
     pub clients: Vec<QuakeClient>,
     pub qtv_stream: Option<QtvStream>,
 }
 
-impl TryFrom<&[u8]> for Status119Response {
-    type Error = anyhow::Error;
+/// Response of `status 119`.
+pub type Status119Response = StatusResponse;
 
-    fn try_from(bytes: &[u8]) -> Result<Self> {
+impl StatusResponse {
+    /// Parse a response to a status request sent with the given flags.
+    ///
+    /// Rows that can not be parsed are skipped, see [`StatusResponse::parse_lenient`].
+    pub fn parse(bytes: &[u8], flags: StatusFlags) -> Result<Self> {
+        Self::parse_lenient(bytes, flags).map(|parsed| parsed.value)
+    }
+
+    /// Parse a response, reporting rows that can not be parsed as warnings.
+    pub fn parse_lenient(bytes: &[u8], flags: StatusFlags) -> Result<Parsed<Self>> {
         // validate header
         let header = vec![255, 255, 255, 255, 110];
 
         if !bytes.starts_with(&header) {
-            return Err(e!("Invalid header"));
+            return Err(Error::InvalidHeader);
         }
 
-        // parse body
+        // parse body, rows are numbered by line
         let body = &bytes[header.len()..];
-        let rows: Vec<Vec<u8>> = Cursor::new(body).split(10).filter_map(|l| l.ok()).collect();
+        let mut rows: Vec<(usize, Vec<u8>)> = Cursor::new(body)
+            .split(10)
+            .filter_map(|l| l.ok())
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty() && l != &[0])
+            .collect();
 
-        const MIN_SERVERINFO_LENGTH: usize = "hostname\\x".len();
+        // parse serverinfo
+        let settings = match flags.has_serverinfo() {
+            true => {
+                const MIN_SERVERINFO_LENGTH: usize = "hostname\\x".len();
 
-        if rows.is_empty() || rows[0].len() < MIN_SERVERINFO_LENGTH {
-            return Err(e!("Invalid body"));
-        }
+                if rows.is_empty() || rows[0].1.len() < MIN_SERVERINFO_LENGTH {
+                    return Err(Error::InvalidBody);
+                }
 
-        // parse serverinfo
-        let settings = quake_serverinfo::Settings::from(rows[0].as_slice());
+                Settings::from(rows.remove(0).1.as_slice())
+            }
+            false => Settings::default(),
+        };
 
         // parse clients and additional info
         let mut clients: Vec<QuakeClient> = vec![];
         let mut qtv_stream: Option<QtvStream> = None;
+        let mut warnings: Vec<ParseWarning> = vec![];
+
+        for (index, row) in rows {
+            let result = match row.starts_with(b"qtv ") {
+                true => QtvStream::try_from(row.as_slice()).map(|s| qtv_stream = Some(s)),
+                false => QuakeClient::try_from_status_row(row.as_slice(), flags)
+                    .map(|client| clients.push(client)),
+            };
 
-        for row in rows {
-            if row.starts_with(b"qtv ") {
-                qtv_stream = QtvStream::try_from(row.as_slice()).ok();
-            } else if let Ok(client) = QuakeClient::try_from(row.as_slice()) {
-                clients.push(client);
+            if let Err(reason) = result {
+                warnings.push(ParseWarning {
+                    row: index,
+                    bytes: row,
+                    reason,
+                });
             }
         }
 
-        Ok(Status119Response {
-            settings,
-            clients,
-            qtv_stream,
+        Ok(Parsed {
+            value: StatusResponse {
+                settings,
+                clients,
+                qtv_stream,
+            },
+            warnings,
         })
     }
 }
 
+/// A row of a response that was skipped since it could not be parsed.
+#[derive(Debug)]
+pub struct ParseWarning {
+    /// Line number within the response body, starting at 0.
+    pub row: usize,
+    pub bytes: Vec<u8>,
+    pub reason: Error,
+}
+
+#[derive(Debug)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl TryFrom<&[u8]> for StatusResponse {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Self::parse(bytes, StatusFlags::FULL)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hostport::Hostport;
     use anyhow::Result;
     use pretty_assertions::assert_eq;
-    use crate::hostport::Hostport;
 
     #[test]
     fn test_try_from() -> Result<()> {
@@ -158,7 +230,7 @@ mod tests {
                         id: 1,
                         name: "zasadzka Qtv (2)".to_string(),
                         number: 2,
-                        address: Hostport{
+                        address: Hostport {
                             host: "zasadzka.pl".to_string(),
                             port: 28000,
                         },
@@ -289,4 +361,139 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_with_flags() -> Result<()> {
+        // serverinfo only
+        {
+            let bytes = b"\xff\xff\xff\xffn\\hostname\\foo\\map\\dm2\n";
+            let res = StatusResponse::parse(bytes, StatusFlags::SERVERINFO)?;
+            assert_eq!(res.settings.map, Some("dm2".to_string()));
+            assert_eq!(res.clients, vec![]);
+            assert_eq!(res.qtv_stream, None);
+        }
+
+        // serverinfo only, no body
+        {
+            let res = StatusResponse::parse(b"\xff\xff\xff\xffn", StatusFlags::SERVERINFO);
+            assert_eq!(res.unwrap_err().to_string(), "Invalid body".to_string());
+        }
+
+        // truncated client and qtv rows are skipped
+        {
+            let bytes = b"\xff\xff\xff\xffn\\hostname\\foo\n63 43 41\nqtv 1\n";
+            let res = StatusResponse::parse(bytes, StatusFlags::FULL)?;
+            assert_eq!(res.settings.hostname, Some("foo".to_string()));
+            assert_eq!(res.clients, vec![]);
+            assert_eq!(res.qtv_stream, None);
+        }
+
+        // old-style: serverinfo and players without teams
+        {
+            let bytes = b"\xff\xff\xff\xffn\\hostname\\foo\n63 43 41 25 \"XantoM\" \"\" 4 4\n";
+            let res = StatusResponse::parse(bytes, StatusFlags::OLDSTYLE)?;
+            assert_eq!(res.settings.hostname, Some("foo".to_string()));
+            assert_eq!(res.clients.len(), 1);
+            assert_eq!(res.clients[0].name, "XantoM");
+            assert_eq!(res.clients[0].team, "");
+        }
+
+        // players and spectators as players (ASE), no serverinfo
+        {
+            let bytes = b"\xff\xff\xff\xffn63 43 41 25 \"XantoM\" \"\" 4 4\n74 S 3 33 \"razor\" \"\" 3 11\n";
+            let flags =
+                StatusFlags::PLAYERS | StatusFlags::SPECTATORS | StatusFlags::SPECTATORS_AS_PLAYERS;
+            let res = StatusResponse::parse(bytes, flags)?;
+            assert_eq!(res.settings, Settings::default());
+            assert_eq!(
+                res.clients,
+                vec![
+                    QuakeClient {
+                        id: 63,
+                        name: "XantoM".to_string(),
+                        frags: 43,
+                        ping: 25,
+                        time: 41,
+                        top_color: 4,
+                        bottom_color: 4,
+                        ..Default::default()
+                    },
+                    QuakeClient {
+                        id: 74,
+                        name: "razor".to_string(),
+                        frags: 0,
+                        ping: 33,
+                        time: 3,
+                        top_color: 3,
+                        bottom_color: 11,
+                        is_spectator: true,
+                        ..Default::default()
+                    },
+                ]
+            );
+        }
+
+        // flags without teams
+        {
+            let bytes = b"\xff\xff\xff\xffn63 43 41 25 \"XantoM\" \"\" 4 4 \"se\"\n";
+            let flags = StatusFlags::PLAYERS | StatusFlags::SHOWFLAGS;
+            let res = StatusResponse::parse(bytes, flags)?;
+            assert_eq!(res.clients[0].team, "");
+            assert_eq!(res.clients[0].auth_cc, "se");
+        }
+
+        // no clients
+        {
+            let res = StatusResponse::parse(b"\xff\xff\xff\xffn", StatusFlags::PLAYERS)?;
+            assert_eq!(res.clients, vec![]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_lenient() -> Result<()> {
+        let bytes = b"\xff\xff\xff\xffn\\hostname\\foo\n63 43 41 25 \"XantoM\" \"\" 4 4 \"red\" \"\"\n63 x 41\n\nqtv 1\n";
+        let res = StatusResponse::parse_lenient(bytes, StatusFlags::FULL)?;
+        assert_eq!(res.value.settings.hostname, Some("foo".to_string()));
+        assert_eq!(res.value.clients.len(), 1);
+        assert_eq!(res.value.qtv_stream, None);
+
+        assert_eq!(res.warnings.len(), 2);
+        assert_eq!(res.warnings[0].row, 2);
+        assert_eq!(res.warnings[0].bytes, b"63 x 41".to_vec());
+        assert_eq!(res.warnings[0].reason.to_string(), "Invalid client");
+        assert_eq!(res.warnings[1].row, 4);
+        assert_eq!(res.warnings[1].bytes, b"qtv 1".to_vec());
+        assert_eq!(res.warnings[1].reason.to_string(), "Invalid qtv stream");
+        Ok(())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[tokio::test]
+    async fn test_status_119_with_retry() -> Result<()> {
+        // server ignoring the first request
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        let address = server.local_addr()?.to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let _ = server.recv_from(&mut buf).await;
+            if let Ok((_, from)) = server.recv_from(&mut buf).await {
+                let _ = server
+                    .send_to(b"\xff\xff\xff\xffn\\hostname\\retry\n", from)
+                    .await;
+            }
+        });
+
+        let policy = RetryPolicy {
+            attempts: 2,
+            timeout: Duration::from_millis(100),
+            backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let res = status_119_with_retry(&address, &policy).await?;
+        assert_eq!(res.attempts, 2);
+        assert_eq!(res.value.settings.hostname, Some("retry".to_string()));
+        Ok(())
+    }
 }
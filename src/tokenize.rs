@@ -1,25 +1,136 @@
+//! Tokenizer following the semantics of `COM_Parse` in the Quake engine.
+//!
+//! see: https://github.com/id-Software/Quake/blob/master/WinQuake/common.c (COM_Parse)
+use std::borrow::Cow;
+
+/// Split a string into tokens, see [`tokens`].
 pub fn tokenize(value: &str) -> Vec<String> {
-    let mut tokens: Vec<String> = vec![];
-    let mut in_quote = false;
-    let mut current_token = "".to_string();
-
-    for c in value.chars() {
-        match c {
-            '"' => in_quote = !in_quote,
-            ' ' => {
-                if in_quote {
-                    current_token.push(c);
-                } else {
-                    tokens.push(current_token);
-                    current_token = "".to_string();
+    tokens(value).map(Cow::into_owned).collect()
+}
+
+/// Iterate over the tokens of a string without allocating.
+///
+/// - Tokens are separated by whitespace (any char up to and including space).
+/// - `// comments` are skipped until the end of the line.
+/// - Quoted strings are single tokens, possibly empty. `\"` is an escaped quote,
+///   only tokens containing it are allocated.
+/// - `{`, `}`, `(`, `)`, `'` and `:` are tokens of their own outside quotes.
+pub fn tokens(value: &str) -> Tokens<'_> {
+    Tokens {
+        rest: value,
+        single_chars: true,
+    }
+}
+
+/// Tokens of a console command, like [`tokens`] but without single char tokens.
+///
+/// QuakeWorld servers tokenize commands (`Cmd_TokenizeString`) this way, e.g.
+/// `connect quake.se:28501` has two arguments.
+pub fn command_tokens(value: &str) -> Tokens<'_> {
+    Tokens {
+        rest: value,
+        single_chars: false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    rest: &'a str,
+    single_chars: bool,
+}
+
+fn is_whitespace(c: char) -> bool {
+    c <= ' '
+}
+
+fn is_single_char(c: char) -> bool {
+    matches!(c, '{' | '}' | '(' | ')' | '\'' | ':')
+}
+
+impl<'a> Tokens<'a> {
+    fn is_single_char(&self, c: char) -> bool {
+        self.single_chars && is_single_char(c)
+    }
+
+    fn quoted(&mut self) -> Cow<'a, str> {
+        // self.rest starts after the opening quote
+        let rest = self.rest;
+        let mut escaped: Option<String> = None;
+        let mut start = 0;
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if rest[i + 1..].starts_with('"') => {
+                    escaped
+                        .get_or_insert_with(String::new)
+                        .push_str(&rest[start..i]);
+                    start = i + 1;
+                    chars.next();
+                }
+                '"' => {
+                    self.rest = &rest[i + 1..];
+                    return match escaped {
+                        Some(mut token) => {
+                            token.push_str(&rest[start..i]);
+                            Cow::Owned(token)
+                        }
+                        None => Cow::Borrowed(&rest[..i]),
+                    };
                 }
+                _ => {}
             }
-            _ => current_token.push(c),
+        }
+
+        // unterminated quote, the token ends with the string
+        self.rest = "";
+        match escaped {
+            Some(mut token) => {
+                token.push_str(&rest[start..]);
+                Cow::Owned(token)
+            }
+            None => Cow::Borrowed(rest),
         }
     }
+}
 
-    tokens.push(current_token);
-    tokens
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // skip whitespace and comments
+        loop {
+            self.rest = self.rest.trim_start_matches(is_whitespace);
+
+            if !self.rest.starts_with("//") {
+                break;
+            }
+
+            self.rest = self.rest.find('\n').map_or("", |pos| &self.rest[pos..]);
+        }
+
+        let c = self.rest.chars().next()?;
+
+        if c == '"' {
+            self.rest = &self.rest[1..];
+            return Some(self.quoted());
+        }
+
+        if self.is_single_char(c) {
+            let (token, rest) = self.rest.split_at(c.len_utf8());
+            self.rest = rest;
+            return Some(Cow::Borrowed(token));
+        }
+
+        // regular word
+        let len = self
+            .rest
+            .find(|c: char| is_whitespace(c) || self.is_single_char(c))
+            .unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(Cow::Borrowed(token))
+    }
 }
 
 #[cfg(test)]
@@ -55,4 +166,48 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_tokens() {
+        let tokens = |value| tokens(value).collect::<Vec<_>>();
+
+        // whitespace
+        assert_eq!(tokens(""), Vec::<Cow<str>>::new());
+        assert_eq!(tokens(" \t\n "), Vec::<Cow<str>>::new());
+        assert_eq!(tokens("  kick\t 12\r\n"), vec!["kick", "12"]);
+
+        // comments
+        assert_eq!(tokens("a // b c\nd"), vec!["a", "d"]);
+        assert_eq!(tokens("a//b"), vec!["a//b"]);
+
+        // quotes
+        assert_eq!(tokens(r#""a b""c"d"#), vec!["a b", "c", "d"]);
+        assert_eq!(tokens(r#"a"b"#), vec![r#"a"b"#]);
+        assert_eq!(tokens(r#"say "unterminated"#), vec!["say", "unterminated"]);
+        assert_eq!(tokens(r#""\s\ razor""#), vec![r"\s\ razor"]);
+        assert_eq!(
+            tokens(r#"say "a \"quoted\" word" x"#),
+            vec!["say", r#"a "quoted" word"#, "x"]
+        );
+
+        // single char tokens
+        assert_eq!(
+            tokens("quake.se:28501 {x}"),
+            vec!["quake.se", ":", "28501", "{", "x", "}"]
+        );
+        assert_eq!(tokens(r#""a:b (c)""#), vec!["a:b (c)"]);
+        assert_eq!(
+            command_tokens("connect quake.se:28501 {x}").collect::<Vec<_>>(),
+            vec!["connect", "quake.se:28501", "{x}"]
+        );
+    }
+
+    #[test]
+    fn test_tokens_borrowed() {
+        let tokens: Vec<Cow<str>> = tokens(r#"a "b c" "d \"e\"""#).collect();
+        assert!(matches!(tokens[0], Cow::Borrowed("a")));
+        assert!(matches!(tokens[1], Cow::Borrowed("b c")));
+        assert!(matches!(tokens[2], Cow::Owned(_)));
+        assert_eq!(tokens[2], r#"d "e""#);
+    }
 }
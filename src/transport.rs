@@ -0,0 +1,157 @@
+//! Sending requests and receiving responses.
+use crate::error::{Error, Result};
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+use crate::net_extra;
+use crate::protocol::Query;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+
+/// Sends a message to an address (host:port) and returns the response.
+pub trait Transport: Send + Sync {
+    fn send_and_receive<'a>(
+        &'a self,
+        address: &'a str,
+        message: &'a [u8],
+        timeout: Duration,
+    ) -> TransportFuture<'a>;
+}
+
+/// Send a query and decode the response.
+pub async fn query<Q: Query>(
+    transport: &dyn Transport,
+    address: &str,
+    query: &Q,
+    timeout: Duration,
+) -> Result<Q::Response> {
+    let bytes = transport
+        .send_and_receive(address, &query.encode(), timeout)
+        .await?;
+    query.decode(&bytes)
+}
+
+/// One UDP socket per request, the default transport.
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+#[derive(Clone, Debug, Default)]
+pub struct UdpTransport;
+
+#[cfg(any(feature = "tokio", feature = "async-io"))]
+impl Transport for UdpTransport {
+    fn send_and_receive<'a>(
+        &'a self,
+        address: &'a str,
+        message: &'a [u8],
+        timeout: Duration,
+    ) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let options = net_extra::ReadOptions {
+                timeout,
+                buffer_size: 64 * 1024, // 64 kb
+            };
+            net_extra::send_and_receive(address, message, options).await
+        })
+    }
+}
+
+/// Address and message of a request.
+type Request = (String, Vec<u8>);
+
+/// Responds with predefined responses, for tests.
+///
+/// Requests without a response fail with [`Error::Timeout`].
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    responses: Mutex<HashMap<Request, Vec<u8>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Respond to `request` sent to `address` with `response`.
+    pub fn insert(&self, address: &str, request: &[u8], response: &[u8]) {
+        if let Ok(mut responses) = self.responses.lock() {
+            responses.insert((address.to_string(), request.to_vec()), response.to_vec());
+        }
+    }
+
+    /// Requests sent so far, as (address, message).
+    pub fn requests(&self) -> Vec<(String, Vec<u8>)> {
+        self.requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send_and_receive<'a>(
+        &'a self,
+        address: &'a str,
+        message: &'a [u8],
+        _timeout: Duration,
+    ) -> TransportFuture<'a> {
+        Box::pin(async move {
+            if let Ok(mut requests) = self.requests.lock() {
+                requests.push((address.to_string(), message.to_vec()));
+            }
+
+            self.responses
+                .lock()
+                .ok()
+                .and_then(|r| r.get(&(address.to_string(), message.to_vec())).cloned())
+                .ok_or(Error::Timeout)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{QtvusersQuery, StatusQuery};
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn test_memory_transport() -> Result<()> {
+        let transport = MemoryTransport::new();
+        let timeout = Duration::from_millis(50);
+        transport.insert(
+            "10.0.0.1:27500",
+            b"\xff\xff\xff\xffstatus 119",
+            b"\xff\xff\xff\xffn\\hostname\\foo\n",
+        );
+
+        let res = query(
+            &transport,
+            "10.0.0.1:27500",
+            &StatusQuery::default(),
+            timeout,
+        )
+        .await?;
+        assert_eq!(res.value.settings.hostname, Some("foo".to_string()));
+
+        let res = query(&transport, "10.0.0.1:27500", &QtvusersQuery, timeout).await;
+        assert!(matches!(res, Err(Error::Timeout)));
+
+        assert_eq!(
+            transport.requests(),
+            vec![
+                (
+                    "10.0.0.1:27500".to_string(),
+                    b"\xff\xff\xff\xffstatus 119".to_vec()
+                ),
+                (
+                    "10.0.0.1:27500".to_string(),
+                    b"\xff\xff\xff\xffqtvusers".to_vec()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+//! Replays the fuzz corpus (`fuzz/corpus/<target>`) through the parsers used
+//! by the fuzz targets, none of them may panic.
+use serverstat::client::QuakeClient;
+use serverstat::qtv::QtvStream;
+use serverstat::status_flags::StatusFlags;
+use serverstat::svc_qtvusers::QtvusersResponse;
+use serverstat::svc_status::StatusResponse;
+use std::fs;
+use std::path::Path;
+
+fn corpus(target: &str) -> Vec<Vec<u8>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(target);
+    let inputs: Vec<Vec<u8>> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err))
+        .map(|entry| fs::read(entry.unwrap().path()).unwrap())
+        .collect();
+    assert!(!inputs.is_empty(), "{} is empty", dir.display());
+    inputs
+}
+
+#[test]
+fn test_status_response() {
+    // first byte is used as status flags
+    for data in corpus("status_response") {
+        if let Some((flags, bytes)) = data.split_first() {
+            let _ = StatusResponse::parse(bytes, StatusFlags::from_bits(*flags));
+        }
+    }
+}
+
+#[test]
+fn test_quake_client() {
+    for data in corpus("quake_client") {
+        let _ = QuakeClient::try_from(data.as_slice());
+    }
+}
+
+#[test]
+fn test_qtv_stream() {
+    for data in corpus("qtv_stream") {
+        let _ = QtvStream::try_from(data.as_slice());
+    }
+}
+
+#[test]
+fn test_qtvusers_response() {
+    for data in corpus("qtvusers_response") {
+        let _ = QtvusersResponse::try_from(data.as_slice());
+    }
+}